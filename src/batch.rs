@@ -0,0 +1,112 @@
+//! Collect the pkg-info files to process in a single run.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+
+/// The filename searched when a directory is provided.
+pub const PKG_INFO_FILENAME: &str = "pkg-info.json";
+
+/// Expand the provided paths into a list of pkg-info files.
+///
+/// - A directory is recursively searched for [`PKG_INFO_FILENAME`] files (hidden folders are skipped).
+/// - A path containing glob metacharacters (`*`, `?` or `[`) is expanded.
+/// - Any other path is used as-is.
+///
+/// The returned list is deduplicated (comparing the canonical paths) while preserving the order.
+pub fn collect_files(paths: &[PathBuf]) -> anyhow::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+
+    for path in paths {
+        let raw_path = path.to_string_lossy();
+        if raw_path.contains(['*', '?', '[']) {
+            let entries = glob::glob(&raw_path)
+                .with_context(|| format!("Invalid glob pattern `{raw_path}`"))?;
+            for entry in entries {
+                let entry = entry.context("Cannot expand glob pattern")?;
+                if entry.is_dir() {
+                    collect_files_in_dir(&entry, &mut files)?;
+                } else {
+                    files.push(entry);
+                }
+            }
+        } else if path.is_dir() {
+            collect_files_in_dir(path, &mut files)?;
+        } else {
+            files.push(path.clone());
+        }
+    }
+
+    let mut seen = std::collections::HashSet::new();
+    files.retain(|file| seen.insert(std::fs::canonicalize(file).unwrap_or_else(|_| file.clone())));
+
+    Ok(files)
+}
+
+fn collect_files_in_dir(dir: &Path, files: &mut Vec<PathBuf>) -> anyhow::Result<()> {
+    let mut entries = std::fs::read_dir(dir)
+        .and_then(|entries| entries.collect::<Result<Vec<_>, _>>())
+        .with_context(|| format!("Cannot list directory {}", dir.display()))?;
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        let path = entry.path();
+        if path.is_dir() {
+            if !entry.file_name().to_string_lossy().starts_with('.') {
+                collect_files_in_dir(&path, files)?;
+            }
+        } else if entry.file_name() == PKG_INFO_FILENAME {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn collect_files_from_dir() {
+        let tree = crate::fs_utils::make_tree(&[
+            "b/pkg-info.json",
+            "a/pkg-info.json",
+            "a/other.json",
+            ".hidden/pkg-info.json",
+        ]);
+        let root = tree.path().to_path_buf();
+
+        let files = collect_files(std::slice::from_ref(&root)).unwrap();
+
+        assert_eq!(
+            files,
+            [root.join("a/pkg-info.json"), root.join("b/pkg-info.json")]
+        );
+    }
+
+    #[test]
+    fn collect_files_from_glob_and_path() {
+        let tree =
+            crate::fs_utils::make_tree(&["a/pkg-info.json", "b/pkg-info.json", "c/custom.json"]);
+        let root = tree.path().to_path_buf();
+
+        let files = collect_files(&[
+            root.join("c/custom.json"),
+            root.join("*/pkg-info.json"),
+            root.join("a/pkg-info.json"),
+            root.join("b/../a/pkg-info.json"),
+            root.join("./c/custom.json"),
+        ])
+        .unwrap();
+
+        assert_eq!(
+            files,
+            [
+                root.join("c/custom.json"),
+                root.join("a/pkg-info.json"),
+                root.join("b/pkg-info.json"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,267 @@
+//! Download a recorded asset, verify it against its digest & optionally extract it.
+
+use std::{
+    ffi::OsStr,
+    fs::{File, OpenOptions},
+    io::{self, BufWriter},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+
+use crate::{pkg_info::VersionedArchEntry, Arch};
+
+/// The env variable set by `docker buildx` to the arch of the image being built.
+const TARGET_ARCH_ENV: &str = "TARGETARCH";
+
+/// Detect the arch of the asset to download from `TARGETARCH`, falling back to the host arch.
+pub fn detect_arch() -> anyhow::Result<Arch> {
+    if let Some(target_arch) = std::env::var(TARGET_ARCH_ENV)
+        .ok()
+        .filter(|arch| !arch.is_empty())
+    {
+        return target_arch
+            .parse()
+            .with_context(|| format!("Invalid arch in env value `{TARGET_ARCH_ENV}`"));
+    }
+    host_arch(std::env::consts::ARCH)
+        .with_context(|| format!("Unsupported host arch `{}`", std::env::consts::ARCH))
+}
+
+fn host_arch(arch: &str) -> Option<Arch> {
+    match arch {
+        "x86_64" => Some(Arch::Amd64),
+        "aarch64" => Some(Arch::Arm64),
+        _ => None,
+    }
+}
+
+/// Download the asset of `entry` to `dest`, failing if it does not match the recorded digest.
+///
+/// The asset is written to a sibling temporary file renamed to `dest` once verified,
+/// an existing `dest` is left untouched on failure. Return the downloaded size.
+///
+/// `http_client` must not decode the asset (see [`crate::prepare_http_client_download`]),
+/// the recorded digest is computed over the bytes as served.
+pub async fn download_entry(
+    http_client: &reqwest::Client,
+    entry: &VersionedArchEntry<'_>,
+    dest: &Path,
+) -> anyhow::Result<usize> {
+    let response = http_client.get(entry.download_url.clone()).send().await?;
+    anyhow::ensure!(
+        response.status() == reqwest::StatusCode::OK,
+        "Invalid response status {} for asset {}",
+        response.status(),
+        entry.filename
+    );
+
+    let tmp_path = crate::fs_utils::tmp_path(dest);
+    let file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&tmp_path)
+        .with_context(|| format!("Cannot create {}", tmp_path.display()))?;
+
+    let res = async {
+        let mut writer = BufWriter::new(file);
+        let (size, digest) = crate::reqwest_utils::download_response(
+            response,
+            entry.digest.algorithm(),
+            &mut writer,
+        )
+        .await
+        .with_context(|| format!("Failed to download asset {}", entry.filename))?;
+        writer
+            .into_inner()
+            .map_err(io::IntoInnerError::into_error)
+            .with_context(|| format!("Cannot write {}", tmp_path.display()))?;
+
+        anyhow::ensure!(
+            digest == entry.digest,
+            "Digest mismatch for asset {}: expected {}, got {digest}",
+            entry.filename,
+            entry.digest
+        );
+
+        std::fs::rename(&tmp_path, dest)
+            .with_context(|| format!("Cannot move the asset to {}", dest.display()))?;
+        Ok(size)
+    }
+    .await;
+    if res.is_err() {
+        let _ = std::fs::remove_file(&tmp_path);
+    }
+    res
+}
+
+/// The supported archive formats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveKind {
+    TarGz,
+    Zip,
+}
+
+impl ArchiveKind {
+    /// Guess the archive format from its filename.
+    pub fn from_filename(filename: &str) -> Option<Self> {
+        let filename = filename.to_ascii_lowercase();
+        if filename.ends_with(".tar.gz") || filename.ends_with(".tgz") {
+            Some(Self::TarGz)
+        } else if filename.ends_with(".zip") {
+            Some(Self::Zip)
+        } else {
+            None
+        }
+    }
+}
+
+/// Extract the file `member` of an archive to `dest`.
+///
+/// `member` is either the path of the file inside the archive or its file name.
+pub fn extract_member(
+    archive: &Path,
+    kind: ArchiveKind,
+    member: &str,
+    dest: &Path,
+) -> anyhow::Result<()> {
+    let archive_file =
+        File::open(archive).with_context(|| format!("Cannot open {}", archive.display()))?;
+
+    match kind {
+        ArchiveKind::TarGz => {
+            let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(archive_file));
+            for entry in archive.entries().context("Reading the tar archive")? {
+                let mut entry = entry.context("Reading the tar archive")?;
+                let path = entry.path()?.into_owned();
+                if entry.header().entry_type().is_file() && is_member(&path, member) {
+                    entry
+                        .unpack(dest)
+                        .with_context(|| format!("Cannot extract {}", path.display()))?;
+                    return Ok(());
+                }
+            }
+        }
+        ArchiveKind::Zip => {
+            let mut archive =
+                zip::ZipArchive::new(archive_file).context("Reading the zip archive")?;
+            let name = archive
+                .file_names()
+                .find(|name| !name.ends_with('/') && is_member(Path::new(name), member))
+                .map(ToOwned::to_owned);
+            if let Some(name) = name {
+                let mut file = archive.by_name(&name)?;
+                let mut dest_file = File::create(dest)
+                    .with_context(|| format!("Cannot create {}", dest.display()))?;
+                std::io::copy(&mut file, &mut dest_file)
+                    .with_context(|| format!("Cannot extract {name}"))?;
+                #[cfg(unix)]
+                if let Some(mode) = file.unix_mode() {
+                    use std::os::unix::fs::PermissionsExt;
+
+                    dest_file.set_permissions(std::fs::Permissions::from_mode(mode & 0o777))?;
+                }
+                return Ok(());
+            }
+        }
+    }
+
+    anyhow::bail!("No file `{member}` in archive {}", archive.display())
+}
+
+fn is_member(path: &Path, member: &str) -> bool {
+    path == Path::new(member) || path.file_name() == Some(OsStr::new(member))
+}
+
+/// The default destination: the file name of `member` (or the asset) in the current directory.
+pub fn default_destination(filename: &str, member: Option<&str>) -> PathBuf {
+    let name = member.unwrap_or(filename);
+    PathBuf::from(Path::new(name).file_name().unwrap_or(OsStr::new(name)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::x86_64("x86_64", Some(Arch::Amd64))]
+    #[case::aarch64("aarch64", Some(Arch::Arm64))]
+    #[case::unsupported("riscv64", None)]
+    fn test_host_arch(#[case] arch: &str, #[case] expected: Option<Arch>) {
+        assert_eq!(host_arch(arch), expected);
+    }
+
+    #[rstest]
+    #[case::tar_gz("hugo_0.119.0_linux-amd64.tar.gz", Some(ArchiveKind::TarGz))]
+    #[case::tgz("foo.TGZ", Some(ArchiveKind::TarGz))]
+    #[case::zip("foo-linux-amd64.zip", Some(ArchiveKind::Zip))]
+    #[case::binary("argocd-linux-amd64", None)]
+    fn test_archive_kind(#[case] filename: &str, #[case] expected: Option<ArchiveKind>) {
+        assert_eq!(ArchiveKind::from_filename(filename), expected);
+    }
+
+    #[test]
+    fn extract_tar_gz_member() {
+        let tree = crate::fs_utils::make_tree(&[]);
+        let root = tree.path();
+        let archive_path = root.join("foo.tar.gz");
+        {
+            let encoder = flate2::write::GzEncoder::new(
+                File::create(&archive_path).unwrap(),
+                flate2::Compression::default(),
+            );
+            let mut builder = tar::Builder::new(encoder);
+            for (path, data) in [("foo-1.0/README.md", "readme"), ("foo-1.0/foo", "binary")] {
+                let mut header = tar::Header::new_gnu();
+                header.set_size(data.len() as u64);
+                header.set_mode(0o755);
+                builder
+                    .append_data(&mut header, path, data.as_bytes())
+                    .unwrap();
+            }
+            builder.into_inner().unwrap().finish().unwrap();
+        }
+
+        let dest = root.join("foo");
+        extract_member(&archive_path, ArchiveKind::TarGz, "foo", &dest).unwrap();
+        assert_eq!(std::fs::read_to_string(&dest).unwrap(), "binary");
+
+        let err = extract_member(&archive_path, ArchiveKind::TarGz, "bar", &root.join("bar"))
+            .unwrap_err();
+        assert!(err.to_string().starts_with("No file `bar` in archive"));
+    }
+
+    #[test]
+    fn extract_zip_member() {
+        let tree = crate::fs_utils::make_tree(&[]);
+        let root = tree.path();
+        let archive_path = root.join("foo.zip");
+        {
+            let mut writer = zip::ZipWriter::new(File::create(&archive_path).unwrap());
+            writer
+                .start_file("bin/foo", zip::write::SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(b"binary").unwrap();
+            writer.finish().unwrap();
+        }
+
+        let dest = root.join("foo");
+        extract_member(&archive_path, ArchiveKind::Zip, "bin/foo", &dest).unwrap();
+        assert_eq!(std::fs::read_to_string(&dest).unwrap(), "binary");
+    }
+
+    #[rstest]
+    #[case::asset("foo-linux-amd64", None, "foo-linux-amd64")]
+    #[case::member("foo.tar.gz", Some("foo-1.0/bin/foo"), "foo")]
+    fn test_default_destination(
+        #[case] filename: &str,
+        #[case] member: Option<&str>,
+        #[case] expected: &str,
+    ) {
+        assert_eq!(default_destination(filename, member), Path::new(expected));
+    }
+}
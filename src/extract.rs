@@ -0,0 +1,157 @@
+//! Extract the information of a recorded asset from a pkg-info.
+
+use std::fmt::Display;
+
+use serde::Serialize;
+use url::Url;
+
+use crate::{Arch, PkgInfo, VersionedArchEntry};
+
+/// The information about the asset of a specific version & arch.
+#[derive(Debug, Serialize, PartialEq, Eq)]
+pub struct ExtractedInfo<'a> {
+    pub name: &'a str,
+    pub version: &'a str,
+    pub download_url: &'a Url,
+    pub filename: &'a str,
+    pub digest_algo: &'static str,
+    pub digest: &'a str,
+}
+
+impl<'a> ExtractedInfo<'a> {
+    /// Extract the info for the provided `version` (default to `latest_version`) and `arch`.
+    pub fn new(
+        pkg_info: &'a PkgInfo<'_>,
+        version: Option<&'a str>,
+        arch: Arch,
+    ) -> Result<Self, ExtractError> {
+        let (version, entry) = find_entry(pkg_info, version, arch)?;
+
+        Ok(Self {
+            name: pkg_info.base.name,
+            version,
+            download_url: &entry.download_url,
+            filename: &entry.filename,
+            digest_algo: entry.digest.algorithm().as_str(),
+            digest: entry.digest.value(),
+        })
+    }
+}
+
+/// Find the recorded asset of `version` (default to `latest_version`) for `arch`.
+pub fn find_entry<'a>(
+    pkg_info: &'a PkgInfo<'_>,
+    version: Option<&'a str>,
+    arch: Arch,
+) -> Result<(&'a str, &'a VersionedArchEntry<'a>), ExtractError> {
+    let version = version
+        .or(pkg_info.base.latest_version.as_deref())
+        .ok_or(ExtractError::MissingLatestVersion)?;
+    let entry = pkg_info
+        .base
+        .versions
+        .as_ref()
+        .and_then(|versions| versions.get(version))
+        .ok_or_else(|| ExtractError::UnknownVersion(version.to_owned()))?
+        .get(&arch)
+        .ok_or_else(|| ExtractError::MissingArch {
+            version: version.to_owned(),
+            arch,
+        })?;
+
+    Ok((version, entry))
+}
+
+/// Display the info as `key=value` lines (suitable for `$GITHUB_OUTPUT`).
+impl Display for ExtractedInfo<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "name={}", self.name)?;
+        writeln!(f, "version={}", self.version)?;
+        writeln!(f, "download_url={}", self.download_url)?;
+        writeln!(f, "filename={}", self.filename)?;
+        writeln!(f, "digest_algo={}", self.digest_algo)?;
+        writeln!(f, "digest={}", self.digest)
+    }
+}
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum ExtractError {
+    #[error("No version provided and `latest_version` is not set")]
+    MissingLatestVersion,
+    #[error("Version `{}` is not listed in `versions`", .0)]
+    UnknownVersion(String),
+    #[error("Version `{}` has no asset for arch `{}`", .version, .arch)]
+    MissingArch { version: String, arch: Arch },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PKG_INFO: &str = r#"{
+  "name": "Foobar",
+  "latest_version": "1.1.0",
+  "versions": {
+    "1.0.0": {
+      "amd64": {
+        "filename": "foobar-amd64",
+        "download_url": "https://example.com/1.0.0/foobar-amd64",
+        "digest": "sha256:e8bf04349572f90e569c5bd46be3f7101e1e289125adb8b9eaba94badba1c43a"
+      }
+    },
+    "1.1.0": {
+      "arm64": {
+        "filename": "foobar-arm64",
+        "download_url": "https://example.com/1.1.0/foobar-arm64",
+        "digest": "sha256:25f5602ea53a18f4d64208c6d135690ace28cda7b89ef1eeccd2e60e6cce2e03"
+      }
+    }
+  },
+  "mode": "bash-command",
+  "command": "true"
+}"#;
+
+    #[test]
+    fn extract_latest_version() {
+        let pkg_info = serde_json::from_str::<PkgInfo>(PKG_INFO).unwrap();
+
+        let info = ExtractedInfo::new(&pkg_info, None, Arch::Arm64).unwrap();
+
+        assert_eq!(
+            info.to_string(),
+            "name=Foobar\n\
+             version=1.1.0\n\
+             download_url=https://example.com/1.1.0/foobar-arm64\n\
+             filename=foobar-arm64\n\
+             digest_algo=sha256\n\
+             digest=25f5602ea53a18f4d64208c6d135690ace28cda7b89ef1eeccd2e60e6cce2e03\n"
+        );
+    }
+
+    #[test]
+    fn extract_specific_version() {
+        let pkg_info = serde_json::from_str::<PkgInfo>(PKG_INFO).unwrap();
+
+        let info = ExtractedInfo::new(&pkg_info, Some("1.0.0"), Arch::Amd64).unwrap();
+
+        assert_eq!(info.version, "1.0.0");
+        assert_eq!(info.filename, "foobar-amd64");
+    }
+
+    #[test]
+    fn extract_errors() {
+        let pkg_info = serde_json::from_str::<PkgInfo>(PKG_INFO).unwrap();
+
+        assert_eq!(
+            ExtractedInfo::new(&pkg_info, None, Arch::Amd64),
+            Err(ExtractError::MissingArch {
+                version: "1.1.0".into(),
+                arch: Arch::Amd64
+            })
+        );
+        assert_eq!(
+            ExtractedInfo::new(&pkg_info, Some("2.0.0"), Arch::Amd64),
+            Err(ExtractError::UnknownVersion("2.0.0".into()))
+        );
+    }
+}
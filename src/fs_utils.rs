@@ -0,0 +1,179 @@
+use std::{
+    fs::{File, OpenOptions, TryLockError},
+    io::{self, BufWriter},
+    path::{Path, PathBuf},
+};
+
+/// Replace the content of `path` with what `write` produce.
+///
+/// The data is written to a sibling temporary file which is synced to disk then renamed over `path`,
+/// so `path` is never left half-written. The permissions of the existing file are preserved.
+pub fn write_atomic(
+    path: &Path,
+    write: impl FnOnce(&mut BufWriter<File>) -> io::Result<()>,
+) -> io::Result<()> {
+    let tmp_path = tmp_path(path);
+
+    let res = (|| {
+        let file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&tmp_path)?;
+        if let Ok(metadata) = std::fs::metadata(path) {
+            file.set_permissions(metadata.permissions())?;
+        }
+
+        let mut writer = BufWriter::new(file);
+        write(&mut writer)?;
+        let file = writer
+            .into_inner()
+            .map_err(io::IntoInnerError::into_error)?;
+        file.sync_all()?;
+
+        std::fs::rename(&tmp_path, path)
+    })();
+    if res.is_err() {
+        let _ = std::fs::remove_file(&tmp_path);
+    }
+    res?;
+
+    // Persist the rename.
+    let parent = path.parent().filter(|p| !p.as_os_str().is_empty());
+    File::open(parent.unwrap_or(Path::new(".")))?.sync_all()
+}
+
+/// The sibling temporary file `path` is written to before being renamed over it.
+pub fn tmp_path(path: &Path) -> PathBuf {
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!(".{file_name}.{}.tmp", std::process::id()))
+}
+
+/// An exclusive advisory lock on a file, released when dropped.
+///
+/// The file is replaced when written with [`write_atomic`], so the lock is only held once it is taken
+/// on the file currently at the path (no `<file>.lock` is left behind).
+#[derive(Debug)]
+pub struct FileLock {
+    _file: File,
+}
+
+impl FileLock {
+    /// Acquire the lock of `path`, waiting for it to be released by another process if needed.
+    pub fn acquire(path: &Path) -> io::Result<Self> {
+        use std::os::unix::fs::MetadataExt;
+
+        loop {
+            let file = File::open(path)?;
+            match file.try_lock() {
+                Ok(()) => (),
+                Err(TryLockError::WouldBlock) => {
+                    log::info!("Waiting for the lock of {} ...", path.display());
+                    file.lock()?;
+                }
+                Err(TryLockError::Error(e)) => return Err(e),
+            }
+
+            // The previous holder may have replaced the file while we were waiting.
+            let (locked, current) = (file.metadata()?, std::fs::metadata(path)?);
+            if (locked.dev(), locked.ino()) == (current.dev(), current.ino()) {
+                return Ok(Self { _file: file });
+            }
+            log::debug!(
+                "{} was replaced while waiting, locking it again",
+                path.display()
+            );
+        }
+    }
+}
+
+/// Create a temporary folder containing the `files` (with `{}` as content), removed when dropped.
+#[cfg(test)]
+pub(crate) fn make_tree(files: &[&str]) -> tempfile::TempDir {
+    let root = tempfile::Builder::new()
+        .prefix("pkg-info-updater-")
+        .tempdir()
+        .unwrap();
+    for file in files {
+        let path = root.path().join(file);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, "{}").unwrap();
+    }
+    root
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    #[test]
+    fn write_atomic_replace_content() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let tree = make_tree(&[]);
+        let root = tree.path();
+        let path = root.join("pkg-info.json");
+        std::fs::write(&path, "old content").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o640)).unwrap();
+
+        write_atomic(&path, |writer| writer.write_all(b"new content")).unwrap();
+
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "new content");
+        assert_eq!(
+            std::fs::metadata(&path).unwrap().permissions().mode() & 0o777,
+            0o640
+        );
+        assert_eq!(std::fs::read_dir(root).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn write_atomic_keep_original_on_error() {
+        let tree = make_tree(&[]);
+        let root = tree.path();
+        let path = root.join("pkg-info.json");
+        std::fs::write(&path, "old content").unwrap();
+
+        let res = write_atomic(&path, |writer| {
+            writer.write_all(b"partial")?;
+            Err(io::Error::other("disk full"))
+        });
+
+        assert!(res.is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "old content");
+        assert_eq!(std::fs::read_dir(root).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn file_lock_is_exclusive() {
+        let tree = make_tree(&["pkg-info.json"]);
+        let path = tree.path().join("pkg-info.json");
+
+        let lock = FileLock::acquire(&path).unwrap();
+        let other = File::open(&path).unwrap();
+        assert!(matches!(other.try_lock(), Err(TryLockError::WouldBlock)));
+
+        drop(lock);
+        assert!(other.try_lock().is_ok());
+        assert_eq!(std::fs::read_dir(tree.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn file_lock_follow_replaced_file() {
+        let tree = make_tree(&["pkg-info.json"]);
+        let path = tree.path().join("pkg-info.json");
+
+        let lock = FileLock::acquire(&path).unwrap();
+        let waiter = std::thread::spawn({
+            let path = path.clone();
+            move || FileLock::acquire(&path).map(drop)
+        });
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        write_atomic(&path, |writer| writer.write_all(b"new content")).unwrap();
+        drop(lock);
+
+        waiter.join().unwrap().unwrap();
+        let replaced = File::open(&path).unwrap();
+        assert!(replaced.try_lock().is_ok());
+    }
+}
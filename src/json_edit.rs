@@ -0,0 +1,502 @@
+//! Edit a JSON document in place, the parts that are not modified are kept byte-for-byte.
+
+use serde::Serialize;
+
+#[derive(Debug, thiserror::Error)]
+pub enum JsonEditError {
+    #[error("Invalid JSON: {}", .0)]
+    Json(#[from] serde_json::Error),
+    #[error("`{}` is not an object", .0)]
+    NotAnObject(String),
+}
+
+/// Where to insert a member missing from an object.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Position<'a> {
+    First,
+    Last,
+    /// After the provided member, at the end if it does not exist.
+    After(&'a str),
+}
+
+/// A JSON document edited in place.
+///
+/// The modified values are serialized with the indentation detected from the document.
+#[derive(Debug, Clone)]
+pub struct JsonDocument {
+    raw: String,
+    /// The indentation unit, `None` for a compact document.
+    indent: Option<String>,
+}
+
+impl JsonDocument {
+    pub fn parse(raw: String) -> Result<Self, JsonEditError> {
+        serde_json::from_str::<serde::de::IgnoredAny>(&raw)?;
+        let indent = detect_indent(&raw);
+
+        Ok(Self { raw, indent })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.raw
+    }
+
+    /// Check if the value at `path` (a list of object keys) is an object.
+    pub fn is_object(&self, path: &[&str]) -> bool {
+        find_object(&parse_spans(&self.raw), path).is_some()
+    }
+
+    /// Set the member `key` of the object at `path`, inserting it at `position` if it does not exist.
+    pub fn set_member(
+        &mut self,
+        path: &[&str],
+        key: &str,
+        value: &impl Serialize,
+        position: Position<'_>,
+    ) -> Result<(), JsonEditError> {
+        let root = parse_spans(&self.raw);
+        let (object, members) =
+            find_object(&root, path).ok_or_else(|| JsonEditError::NotAnObject(pointer(path)))?;
+
+        if let Some(member) = members.iter().find(|member| member.key == key) {
+            let indent = line_indent(&self.raw, member.key_start);
+            let value = self.render(value, indent)?;
+            self.raw
+                .replace_range(member.value.start..member.value.end, &value);
+            return Ok(());
+        }
+
+        let key = serde_json::to_string(key)?;
+        let Some(first) = members.first() else {
+            // Empty object.
+            let edit = match &self.indent {
+                Some(unit) => {
+                    let object_indent = leading_whitespaces(&self.raw, object.start);
+                    let indent = format!("{object_indent}{unit}");
+                    let value = self.render(value, Some(&indent))?;
+                    format!("\n{indent}{key}: {value}\n{object_indent}")
+                }
+                None => format!("{key}:{}", serde_json::to_string(value)?),
+            };
+            self.raw
+                .replace_range(object.start + 1..object.end - 1, &edit);
+            return Ok(());
+        };
+
+        let indent = line_indent(&self.raw, first.key_start);
+        let separator = &self.raw[first.key_end..first.value.start];
+        let entry = format!("{key}{separator}{}", self.render(value, indent)?);
+        let delimiter = match (indent, members.get(1)) {
+            (Some(indent), _) => format!(",\n{indent}"),
+            (None, Some(second)) => self.raw[first.value.end..second.key_start].to_owned(),
+            (None, None) => ", ".to_owned(),
+        };
+
+        let anchor = match position {
+            Position::First => None,
+            Position::Last => members.last(),
+            Position::After(after) => members
+                .iter()
+                .find(|member| member.key == after)
+                .or(members.last()),
+        };
+        match anchor {
+            Some(anchor) => self
+                .raw
+                .insert_str(anchor.value.end, &format!("{delimiter}{entry}")),
+            None => self
+                .raw
+                .insert_str(first.key_start, &format!("{entry}{delimiter}")),
+        }
+
+        Ok(())
+    }
+
+    /// Remove the member `key` of the object at `path`, return `false` if it does not exist.
+    pub fn remove_member(&mut self, path: &[&str], key: &str) -> Result<bool, JsonEditError> {
+        let root = parse_spans(&self.raw);
+        let (object, members) =
+            find_object(&root, path).ok_or_else(|| JsonEditError::NotAnObject(pointer(path)))?;
+        let Some(index) = members.iter().position(|member| member.key == key) else {
+            return Ok(false);
+        };
+
+        let range = match (index.checked_sub(1), members.get(index + 1)) {
+            (Some(previous), _) => members[previous].value.end..members[index].value.end,
+            (None, Some(next)) => members[index].key_start..next.key_start,
+            (None, None) => object.start + 1..object.end - 1,
+        };
+        self.raw.replace_range(range, "");
+
+        Ok(true)
+    }
+
+    /// Reorder the members of the object at `path`, return `false` if they were already sorted.
+    ///
+    /// The members are moved verbatim, the separators between them are kept in place.
+    pub fn sort_members(
+        &mut self,
+        path: &[&str],
+        mut compare: impl FnMut(&str, &str) -> std::cmp::Ordering,
+    ) -> Result<bool, JsonEditError> {
+        let root = parse_spans(&self.raw);
+        let (_, members) =
+            find_object(&root, path).ok_or_else(|| JsonEditError::NotAnObject(pointer(path)))?;
+
+        let mut sorted = members.iter().collect::<Vec<_>>();
+        sorted.sort_by(|a, b| compare(&a.key, &b.key));
+        if sorted
+            .iter()
+            .zip(members)
+            .all(|(sorted, member)| std::ptr::eq(*sorted, member))
+        {
+            return Ok(false);
+        }
+
+        let mut raw = String::with_capacity(self.raw.len());
+        let mut last_end = 0;
+        for (slot, member) in members.iter().zip(sorted) {
+            raw.push_str(&self.raw[last_end..slot.key_start]);
+            raw.push_str(&self.raw[member.key_start..member.value.end]);
+            last_end = slot.value.end;
+        }
+        raw.push_str(&self.raw[last_end..]);
+        self.raw = raw;
+
+        Ok(true)
+    }
+
+    /// Serialize `value`, `indent` is the indentation of the line where it is placed (`None` when inlined).
+    fn render(&self, value: &impl Serialize, indent: Option<&str>) -> serde_json::Result<String> {
+        let (Some(unit), Some(indent)) = (&self.indent, indent) else {
+            return serde_json::to_string(value);
+        };
+
+        let mut buf = Vec::new();
+        let formatter = serde_json::ser::PrettyFormatter::with_indent(unit.as_bytes());
+        value.serialize(&mut serde_json::Serializer::with_formatter(
+            &mut buf, formatter,
+        ))?;
+        let rendered = String::from_utf8(buf).expect("serde_json produce valid UTF-8");
+
+        Ok(rendered.replace('\n', &format!("\n{indent}")))
+    }
+}
+
+/// The indentation unit of a document, `None` if the document is on a single line.
+fn detect_indent(raw: &str) -> Option<String> {
+    if !raw.trim().contains('\n') {
+        return None;
+    }
+    let unit = raw
+        .lines()
+        .skip(1)
+        .map(|line| &line[..line.len() - line.trim_start().len()])
+        .find(|indent| !indent.is_empty())
+        .unwrap_or("  ");
+
+    Some(unit.to_owned())
+}
+
+/// The whitespaces preceding `pos` on its line, `None` if something else precede it.
+fn line_indent(raw: &str, pos: usize) -> Option<&str> {
+    let line_start = raw[..pos].rfind('\n').map_or(0, |i| i + 1);
+    let prefix = &raw[line_start..pos];
+
+    prefix
+        .bytes()
+        .all(|c| c.is_ascii_whitespace())
+        .then_some(prefix)
+}
+
+/// The whitespaces at the start of the line containing `pos`.
+fn leading_whitespaces(raw: &str, pos: usize) -> &str {
+    let line_start = raw[..pos].rfind('\n').map_or(0, |i| i + 1);
+    let line = &raw[line_start..pos];
+
+    &line[..line.len() - line.trim_start().len()]
+}
+
+fn pointer(path: &[&str]) -> String {
+    path.iter().map(|key| format!("/{key}")).collect()
+}
+
+fn find_object<'n>(node: &'n Node, path: &[&str]) -> Option<(&'n Node, &'n [Member])> {
+    let NodeKind::Object(members) = &node.kind else {
+        return None;
+    };
+    match path.split_first() {
+        None => Some((node, members)),
+        Some((key, rest)) => members
+            .iter()
+            .find(|member| member.key == *key)
+            .and_then(|member| find_object(&member.value, rest)),
+    }
+}
+
+/// The location of a value in a JSON document.
+#[derive(Debug)]
+pub(crate) struct Node {
+    /// The offset of the first byte of the value.
+    pub start: usize,
+    /// The offset after the last byte of the value.
+    pub end: usize,
+    pub kind: NodeKind,
+}
+
+#[derive(Debug)]
+pub(crate) enum NodeKind {
+    Object(Vec<Member>),
+    Array(Vec<Node>),
+    Scalar,
+}
+
+#[derive(Debug)]
+pub(crate) struct Member {
+    pub key: String,
+    pub key_start: usize,
+    pub key_end: usize,
+    pub value: Node,
+}
+
+/// Locate every value of a valid JSON document.
+pub(crate) fn parse_spans(raw: &str) -> Node {
+    Scanner { raw, pos: 0 }.value()
+}
+
+struct Scanner<'a> {
+    raw: &'a str,
+    pos: usize,
+}
+
+impl Scanner<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.raw.as_bytes().get(self.pos).copied()
+    }
+
+    fn skip_whitespaces(&mut self) {
+        while self.peek().is_some_and(|c| c.is_ascii_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    fn value(&mut self) -> Node {
+        self.skip_whitespaces();
+        let start = self.pos;
+
+        let kind = match self.peek() {
+            Some(b'{') => {
+                self.pos += 1;
+                let mut members = Vec::new();
+                while self.next_item(b'}') {
+                    let key_start = self.pos;
+                    let key = self.string();
+                    let key_end = self.pos;
+                    self.skip_whitespaces();
+                    // Skip the `:` separator.
+                    self.pos += 1;
+                    members.push(Member {
+                        key,
+                        key_start,
+                        key_end,
+                        value: self.value(),
+                    });
+                }
+                NodeKind::Object(members)
+            }
+            Some(b'[') => {
+                self.pos += 1;
+                let mut items = Vec::new();
+                while self.next_item(b']') {
+                    items.push(self.value());
+                }
+                NodeKind::Array(items)
+            }
+            Some(b'"') => {
+                self.string();
+                NodeKind::Scalar
+            }
+            _ => {
+                while self
+                    .peek()
+                    .is_some_and(|c| !matches!(c, b',' | b'}' | b']') && !c.is_ascii_whitespace())
+                {
+                    self.pos += 1;
+                }
+                NodeKind::Scalar
+            }
+        };
+
+        Node {
+            start,
+            end: self.pos,
+            kind,
+        }
+    }
+
+    /// Move to the next item of a container, return `false` when the end is reached.
+    fn next_item(&mut self, end: u8) -> bool {
+        loop {
+            self.skip_whitespaces();
+            match self.peek() {
+                None => return false,
+                Some(c) if c == end => {
+                    self.pos += 1;
+                    return false;
+                }
+                Some(b',') => self.pos += 1,
+                Some(_) => return true,
+            }
+        }
+    }
+
+    fn string(&mut self) -> String {
+        let start = self.pos;
+
+        self.pos += 1;
+        while let Some(c) = self.peek() {
+            self.pos += 1;
+            match c {
+                b'\\' => self.pos += 1,
+                b'"' => break,
+                _ => (),
+            }
+        }
+
+        serde_json::from_str(&self.raw[start..self.pos]).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    use rstest::rstest;
+
+    const DOC: &str = r#"{
+    "name": "Foo",
+    "x-team": { "owner": "infra" },
+    "latest_version": "1.0.0",
+    "versions": {
+        "1.0.0": {"amd64": "a"}
+    }
+}
+"#;
+
+    #[test]
+    fn replace_member_keep_the_rest() {
+        let mut doc = JsonDocument::parse(DOC.to_owned()).unwrap();
+
+        doc.set_member(&[], "latest_version", &"1.1.0", Position::Last)
+            .unwrap();
+
+        assert_eq!(doc.as_str(), DOC.replace(r#""1.0.0","#, r#""1.1.0","#));
+    }
+
+    #[test]
+    fn insert_member_with_detected_indent() {
+        let mut doc = JsonDocument::parse(DOC.to_owned()).unwrap();
+
+        doc.set_member(
+            &["versions"],
+            "1.1.0",
+            &json!({"amd64": "b"}),
+            Position::Last,
+        )
+        .unwrap();
+
+        assert_eq!(
+            doc.as_str(),
+            r#"{
+    "name": "Foo",
+    "x-team": { "owner": "infra" },
+    "latest_version": "1.0.0",
+    "versions": {
+        "1.0.0": {"amd64": "a"},
+        "1.1.0": {
+            "amd64": "b"
+        }
+    }
+}
+"#
+        );
+    }
+
+    #[rstest]
+    #[case::first(Position::First, "{\n  \"b\": 2,\n  \"a\": 1,\n  \"c\": 3\n}")]
+    #[case::last(Position::Last, "{\n  \"a\": 1,\n  \"c\": 3,\n  \"b\": 2\n}")]
+    #[case::after(Position::After("a"), "{\n  \"a\": 1,\n  \"b\": 2,\n  \"c\": 3\n}")]
+    #[case::after_missing(Position::After("z"), "{\n  \"a\": 1,\n  \"c\": 3,\n  \"b\": 2\n}")]
+    fn insert_member_at_position(#[case] position: Position, #[case] expected: &str) {
+        let mut doc = JsonDocument::parse("{\n  \"a\": 1,\n  \"c\": 3\n}".to_owned()).unwrap();
+
+        doc.set_member(&[], "b", &2, position).unwrap();
+
+        assert_eq!(doc.as_str(), expected);
+    }
+
+    #[rstest]
+    #[case::pretty(
+        "{\n\t\"a\": {}\n}",
+        "{\n\t\"a\": {\n\t\t\"b\": [\n\t\t\t1\n\t\t]\n\t}\n}"
+    )]
+    #[case::compact(r#"{"a":{}}"#, r#"{"a":{"b":[1]}}"#)]
+    fn insert_member_in_empty_object(#[case] raw: &str, #[case] expected: &str) {
+        let mut doc = JsonDocument::parse(raw.to_owned()).unwrap();
+
+        doc.set_member(&["a"], "b", &[1], Position::Last).unwrap();
+
+        assert_eq!(doc.as_str(), expected);
+    }
+
+    #[rstest]
+    #[case::first("a", "{\n  \"b\": 2,\n  \"c\": 3\n}")]
+    #[case::middle("b", "{\n  \"a\": 1,\n  \"c\": 3\n}")]
+    #[case::last("c", "{\n  \"a\": 1,\n  \"b\": 2\n}")]
+    fn remove_member(#[case] key: &str, #[case] expected: &str) {
+        let mut doc =
+            JsonDocument::parse("{\n  \"a\": 1,\n  \"b\": 2,\n  \"c\": 3\n}".to_owned()).unwrap();
+
+        assert!(doc.remove_member(&[], key).unwrap());
+
+        assert_eq!(doc.as_str(), expected);
+    }
+
+    #[test]
+    fn remove_only_member() {
+        let mut doc = JsonDocument::parse(r#"{"a": {"b": 1}}"#.to_owned()).unwrap();
+
+        assert!(doc.remove_member(&["a"], "b").unwrap());
+        assert!(!doc.remove_member(&["a"], "b").unwrap());
+
+        assert_eq!(doc.as_str(), r#"{"a": {}}"#);
+    }
+
+    #[test]
+    fn sort_members_verbatim() {
+        let mut doc = JsonDocument::parse(
+            "{\n  \"b\": {\"x\": 1},\n  \"c\": 3,\n  \"a\": [ 1 ]\n}".to_owned(),
+        )
+        .unwrap();
+
+        assert!(doc.sort_members(&[], str::cmp).unwrap());
+        assert_eq!(
+            doc.as_str(),
+            "{\n  \"a\": [ 1 ],\n  \"b\": {\"x\": 1},\n  \"c\": 3\n}"
+        );
+        assert!(!doc.sort_members(&[], str::cmp).unwrap());
+    }
+
+    #[test]
+    fn edit_missing_object() {
+        let mut doc = JsonDocument::parse(r#"{"a": null}"#.to_owned()).unwrap();
+
+        assert!(!doc.is_object(&["a"]));
+        assert!(matches!(
+            doc.set_member(&["a"], "b", &1, Position::Last),
+            Err(JsonEditError::NotAnObject(path)) if path == "/a"
+        ));
+    }
+}
@@ -1,10 +1,24 @@
+pub mod batch;
+pub mod download;
+pub mod extract;
+mod fs_utils;
+pub mod json_edit;
 pub mod pkg_info;
+pub mod pr_body;
+pub mod report;
 mod reqwest_utils;
+pub mod schema;
 mod serde_utils;
-mod version;
+pub mod validate;
+pub mod verify;
+pub mod version;
 
+pub use extract::{ExtractError, ExtractedInfo};
+pub use fs_utils::{write_atomic, FileLock};
 pub use pkg_info::{
-    Arch, Base as PkgInfoBase, BashCmdReleaseHandler, Digest, GithubReleaseHandler,
-    JqScriptReleaseHandler, Mode as PkgInfoMode, ModeGetLatestVersion, PkgInfo, PkgOption,
-    VersionedArchEntry,
+    Arch, Base as PkgInfoBase, BashCmdReleaseHandler, CheckOutcome, Digest, GithubReleaseHandler,
+    GithubTokenFile, GithubVersionSource, JqScriptReleaseHandler, Mode as PkgInfoMode, ModeContext,
+    ModeGetLatestVersion, PkgInfo, PkgOption, PrereleasePolicy, Retention, VersionedArchEntry,
 };
+pub use reqwest_utils::{prepare_http_client_download, prepare_http_client_json};
+pub use version::{ParsedVersion, Version, VersionTransform};
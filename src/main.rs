@@ -1,52 +1,954 @@
 use std::{
     borrow::Cow,
+    num::NonZeroUsize,
     path::{Path, PathBuf},
+    process::ExitCode,
+    sync::atomic::AtomicU64,
+    time::Instant,
 };
 
 use anyhow::Context;
-use clap::Parser;
+use clap::{Parser, Subcommand};
 
-use pkg_info_updater::PkgInfo;
+use pkg_info_updater::{
+    batch,
+    download::{self, ArchiveKind},
+    json_edit::{JsonDocument, Position},
+    pkg_info::VersionContent,
+    pr_body::PrBody,
+    prepare_http_client_download, prepare_http_client_json,
+    report::{PackageReport, RunReport, Timings},
+    schema, validate, verify, version, write_atomic, Arch, ExtractedInfo, FileLock,
+    GithubReleaseHandler, GithubTokenFile, ModeContext, PkgInfo, PkgInfoBase, PkgInfoMode, Version,
+};
 
 #[derive(Debug, Parser)]
 struct Args {
     /// The path to the pkg info file.
-    #[arg(long = "file", short = 'f', default_value = "pkg-info.json")]
-    file: PathBuf,
-    /// Path to a temporary folder.
-    #[arg(long = "tmp-dir", default_value = "/tmp")]
+    ///
+    /// Can be repeated, a directory is searched for `pkg-info.json` files and glob patterns are expanded.
+    #[arg(
+        long = "file",
+        short = 'f',
+        default_value = "pkg-info.json",
+        global = true
+    )]
+    files: Vec<PathBuf>,
+    /// Path to a temporary folder, a sub-folder named after the package is used when processing several files.
+    #[arg(long = "tmp-dir", default_value = "/tmp", global = true)]
     tmp_dir: PathBuf,
     /// Enable test mode (do not require a clean working env).
-    #[arg(long = "test")]
+    #[arg(long = "test", global = true)]
     test: bool,
+    /// Write a JSON report describing what changed during an `update` or `check` run.
+    #[arg(long = "report", global = true)]
+    report: Option<PathBuf>,
+    /// Accept the assets of an already recorded version re-published upstream with a different digest or URL.
+    #[arg(long = "allow-republish", global = true)]
+    allow_republish: bool,
+    /// Maximum number of packages processed concurrently.
+    #[arg(long = "jobs", short = 'j', default_value = "4", global = true)]
+    jobs: NonZeroUsize,
+    /// The base URL of the GitHub API used by every `github-release` package, overriding their `api_base_url`.
+    ///
+    /// The packages without `api_base_url` otherwise use the env variable `GITHUB_API_URL` when set.
+    #[arg(long = "github-api-url", global = true)]
+    github_api_url: Option<url::Url>,
+    /// The GitHub API from the env variable `GITHUB_API_URL`.
+    #[arg(skip)]
+    env_github_api_url: Option<url::Url>,
+    /// A file containing the token of a GitHub host as `[HOST=]PATH`, the host default to `github.com`.
+    ///
+    /// Can be repeated. Otherwise the token is read from `GH_TOKEN` or `GITHUB_TOKEN` for `github.com`,
+    /// `GH_ENTERPRISE_TOKEN` or `GITHUB_ENTERPRISE_TOKEN` for the other hosts, then from the gh CLI `hosts.yml`.
+    #[arg(long = "github-token-file", global = true)]
+    github_token_files: Vec<GithubTokenFile>,
+    /// The command to execute (default to `update`).
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, Default, Clone, Subcommand)]
+enum Command {
+    /// Update the pkg info file with the latest version.
+    #[default]
+    Update,
+    /// Check if a new version is available without modifying the pkg info file.
+    ///
+    /// The outcome is printed on stdout as `key=value` lines and reflected by the exit code:
+    /// `0` when up-to-date, `10` when a new version is available,
+    /// `11` when the assets of the latest version have changed.
+    Check,
+    /// Scaffold a pkg info file from the latest release of a GitHub repository.
+    ///
+    /// The linux amd64 & arm64 assets are guessed from common naming conventions,
+    /// review the generated `arch_asset_patterns` before using it.
+    Init(InitArgs),
+    /// Resolve a specific upstream version and record it in the pkg info file.
+    ///
+    /// The `latest_version` is left untouched unless `--set-latest` is provided.
+    AddVersion(AddVersionArgs),
+    /// Remove the recorded versions not kept by the `retention` policy of the pkg info file.
+    ///
+    /// The `latest_version` is never removed.
+    Prune(PruneArgs),
+    /// Re-sort the `versions` map of the pkg info files in version order.
+    ///
+    /// The entries are moved as-is, files written by older releases are sorted as plain strings.
+    SortVersions,
+    /// Extract the asset information of a recorded version.
+    ///
+    /// Print `name`, `version`, `download_url`, `filename`, `digest_algo` & `digest`.
+    Extract(ExtractArgs),
+    /// Render the Markdown body of a pull-request going from an old pkg info file to the new one.
+    ///
+    /// List the version change, the assets of each arch with their digest
+    /// and the upstream release link & notes when the mode can retrieve them.
+    PrBody(PrBodyArgs),
+    /// Download a recorded asset and verify it against its digest.
+    ///
+    /// The arch default to `TARGETARCH` or the host arch, the archive member
+    /// selected by `--extract` is placed at the destination instead of the asset.
+    Download(DownloadArgs),
+    /// Re-download the recorded assets and check them against their recorded digest.
+    ///
+    /// Report unreachable assets, digest mismatches and size changes (against the announced `Content-Length`).
+    Verify(VerifyArgs),
+    /// Validate the pkg info files against the schema & the expected types.
+    ///
+    /// Every problem found is reported with its JSON path & position.
+    Validate,
+    /// Print the JSON schema of a pkg info file generated from the types used by this program.
+    Schema,
+}
+
+#[derive(Debug, Clone, clap::Args)]
+struct InitArgs {
+    /// The GitHub repository path (`owner/repo`).
+    #[arg(long = "github")]
+    github: String,
+    /// The name of the package (default to the repository name).
+    #[arg(long = "name")]
+    name: Option<String>,
+    /// Overwrite the pkg info file if it already exist.
+    #[arg(long = "force")]
+    force: bool,
+}
+
+#[derive(Debug, Clone, clap::Args)]
+struct AddVersionArgs {
+    /// The upstream version to record.
+    version: String,
+    /// Also set the recorded version as the `latest_version`.
+    #[arg(long = "set-latest")]
+    set_latest: bool,
+    /// Record the version even if it does not satisfy the `version_constraint` of the pkg info.
+    #[arg(long = "ignore-version-constraint")]
+    ignore_version_constraint: bool,
+}
+
+#[derive(Debug, Clone, clap::Args)]
+struct PruneArgs {
+    /// Only print the versions that would be removed.
+    #[arg(long = "dry-run")]
+    dry_run: bool,
 }
 
-fn main() -> anyhow::Result<()> {
+#[derive(Debug, Clone, clap::Args)]
+struct ExtractArgs {
+    /// The version to extract (default to `latest_version`).
+    #[arg(long = "version")]
+    version: Option<String>,
+    /// The arch of the asset to extract.
+    #[arg(long = "arch", default_value = "amd64")]
+    arch: Arch,
+    /// Print the information as a JSON object instead of `key=value` lines.
+    #[arg(long = "json")]
+    json: bool,
+    /// Append the `key=value` lines to the file pointed by the env variable `GITHUB_OUTPUT`.
+    #[arg(long = "github-output")]
+    github_output: bool,
+}
+
+#[derive(Debug, Clone, clap::Args)]
+struct PrBodyArgs {
+    /// The path to the pkg info file before the update (`--file` being the updated one).
+    #[arg(long = "old")]
+    old: PathBuf,
+    /// Write the body to this file instead of stdout.
+    #[arg(long = "output", short = 'o')]
+    output: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, clap::Args)]
+struct DownloadArgs {
+    /// The version to download (default to `latest_version`).
+    #[arg(long = "version")]
+    version: Option<String>,
+    /// The arch of the asset to download (default to `TARGETARCH` or the host arch).
+    #[arg(long = "arch")]
+    arch: Option<Arch>,
+    /// Extract this file from the downloaded archive (tar.gz or zip), by path or file name.
+    #[arg(long = "extract")]
+    extract: Option<String>,
+    /// Where to place the downloaded (or extracted) file (default to its file name in the current directory).
+    #[arg(long = "output", short = 'o')]
+    output: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, clap::Args)]
+struct VerifyArgs {
+    /// The version to verify (can be repeated, default to every recorded version).
+    #[arg(long = "version")]
+    versions: Vec<String>,
+    /// The arch to verify (can be repeated, default to every recorded arch).
+    #[arg(long = "arch")]
+    arches: Vec<Arch>,
+}
+
+fn main() -> anyhow::Result<ExitCode> {
     init_log();
-    let args = Args::parse();
+    let mut args = Args::parse();
+    args.env_github_api_url = github_api_url_from_env()?;
 
     log::trace!("args={args:#?}");
-    let raw_data = std::fs::read_to_string(&args.file).context("Reading the data")?;
+
+    match args.command.clone().unwrap_or_default() {
+        command @ (Command::Update | Command::Check) => process_packages(&args, &command),
+        Command::Init(init_args) => init(&args, &init_args).map(|_| ExitCode::SUCCESS),
+        Command::AddVersion(add_version_args) => {
+            add_version(&args, &add_version_args).map(|_| ExitCode::SUCCESS)
+        }
+        Command::Prune(prune_args) => prune(&args, &prune_args).map(|_| ExitCode::SUCCESS),
+        Command::SortVersions => sort_versions(&args).map(|_| ExitCode::SUCCESS),
+        Command::Extract(extract_args) => {
+            let file = args.single_file()?;
+            let raw_data = std::fs::read_to_string(file).context("Reading the data")?;
+            let pkg_info =
+                serde_json::from_str::<PkgInfo>(&raw_data).context("Deserializing the data")?;
+            log::trace!("pkg_info={pkg_info:#?}");
+
+            extract(&pkg_info, &extract_args).map(|_| ExitCode::SUCCESS)
+        }
+        Command::PrBody(pr_body_args) => pr_body(&args, &pr_body_args).map(|_| ExitCode::SUCCESS),
+        Command::Download(download_args) => {
+            download_asset(&args, &download_args).map(|_| ExitCode::SUCCESS)
+        }
+        Command::Verify(verify_args) => verify(&args, &verify_args).map(|_| ExitCode::SUCCESS),
+        Command::Validate => validate(&args).map(|_| ExitCode::SUCCESS),
+        Command::Schema => {
+            print!("{}", schema::generate_pretty());
+            Ok(ExitCode::SUCCESS)
+        }
+    }
+}
+
+/// The GitHub API URL from the env variable `GITHUB_API_URL` (set by GitHub Actions).
+fn github_api_url_from_env() -> anyhow::Result<Option<url::Url>> {
+    std::env::var("GITHUB_API_URL")
+        .ok()
+        .filter(|raw| !raw.is_empty())
+        .map(|raw| {
+            raw.parse()
+                .with_context(|| format!("Invalid URL in env variable `GITHUB_API_URL`: {raw}"))
+        })
+        .transpose()
+}
+
+impl Args {
+    fn single_file(&self) -> anyhow::Result<&Path> {
+        match self.files.as_slice() {
+            [file] => Ok(file),
+            _ => anyhow::bail!("This command only accept a single pkg info file"),
+        }
+    }
+}
+
+fn process_packages(args: &Args, command: &Command) -> anyhow::Result<ExitCode> {
+    use futures::StreamExt;
+
+    let started_at = Instant::now();
+    let files = batch::collect_files(&args.files)?;
+    anyhow::ensure!(!files.is_empty(), "No pkg info file found");
+
+    let http_client = prepare_http_client_json()
+        .build()
+        .context("Failed to build HTTP client")?;
+    let download_client = prepare_http_client_download()
+        .build()
+        .context("Failed to build HTTP client")?;
+    let downloaded_bytes = AtomicU64::new(0);
+    let context = ModeContext {
+        tmp_dir: &args.tmp_dir,
+        in_test_mode: args.test,
+        http_client: &http_client,
+        download_client: &download_client,
+        downloaded_bytes: &downloaded_bytes,
+        github_api_url: args.github_api_url.as_ref(),
+        env_github_api_url: args.env_github_api_url.as_ref(),
+        github_token_files: &args.github_token_files,
+    };
+
+    let isolate_tmp_dir = files.len() > 1;
+    let tokio_runtime = tokio::runtime::Runtime::new()?;
+    let mut results = tokio_runtime.block_on(
+        futures::stream::iter(&files)
+            .map(|file| {
+                process_package(
+                    command,
+                    file,
+                    context,
+                    args.allow_republish,
+                    isolate_tmp_dir,
+                )
+            })
+            .buffered(args.jobs.get())
+            .collect::<Vec<_>>(),
+    );
+
+    if let Some(report_path) = &args.report {
+        let command_name = match command {
+            Command::Check => "check",
+            _ => "update",
+        };
+        let run_report = RunReport::new(
+            command_name,
+            files.iter().map(PathBuf::as_path).zip(&results),
+            started_at.elapsed().as_millis() as u64,
+        );
+        let raw_report =
+            serde_json::to_string_pretty(&run_report).context("Serializing the report")?;
+        dump_data_to_file(report_path, raw_report.as_bytes()).context("Writing the report")?;
+    }
+
+    if files.len() == 1 {
+        let report = results.pop().expect("One result per file")?;
+        if matches!(command, Command::Check) {
+            print_check_summary(&report);
+        }
+        return Ok(check_exit_code(command, [&report]));
+    }
+
+    let mut failed = 0;
+    for (file, result) in files.iter().zip(&results) {
+        match result {
+            Ok(report) => println!("{}: {report}", file.display()),
+            Err(e) => {
+                failed += 1;
+                println!("{}: failed: {e:#}", file.display());
+            }
+        }
+    }
+    anyhow::ensure!(
+        failed == 0,
+        "{failed} of {} package(s) failed",
+        results.len()
+    );
+
+    Ok(check_exit_code(command, results.iter().flatten()))
+}
+
+async fn process_package(
+    command: &Command,
+    file: &Path,
+    context: ModeContext<'_>,
+    allow_republish: bool,
+    isolate_tmp_dir: bool,
+) -> anyhow::Result<PackageReport> {
+    log::info!("Processing {}", file.display());
+    let started_at = Instant::now();
+    let raw_data = std::fs::read_to_string(file).context("Reading the data")?;
     let pkg_info = serde_json::from_str::<PkgInfo>(&raw_data).context("Deserializing the data")?;
 
     log::trace!("pkg_info={pkg_info:#?}");
 
-    let fut = pkg_info
+    // The packages of a batch are processed concurrently, each get its own temporary folder.
+    let tmp_dir = if isolate_tmp_dir {
+        let tmp_dir = context.tmp_dir.join(pkg_info.base.name.replace('/', "_"));
+        std::fs::create_dir_all(&tmp_dir)
+            .with_context(|| format!("Cannot create {}", tmp_dir.display()))?;
+        Cow::Owned(tmp_dir)
+    } else {
+        Cow::Borrowed(context.tmp_dir)
+    };
+    let downloaded_bytes = AtomicU64::new(0);
+    let context = ModeContext {
+        tmp_dir: &tmp_dir,
+        downloaded_bytes: &downloaded_bytes,
+        ..context
+    };
+    let (version, content) = pkg_info
         .mode
-        .get_latest_version(&pkg_info.option, &args.tmp_dir, args.test);
+        .get_latest_version(&pkg_info.option, context)
+        .await?;
+    let resolve_duration = started_at.elapsed();
+
+    let mut report = PackageReport::new(file, &pkg_info, version.as_str(), &content);
+    log::info!(
+        "{}: {} (version {})",
+        pkg_info.base.name,
+        report.outcome,
+        version.as_str()
+    );
+
+    if matches!(command, Command::Update) {
+        let lock = lock_pkg_info_async(file).await?;
+        modify_pkg_info(file, lock, |pkg_info, document| {
+            ensure_not_republished(pkg_info, version.as_str(), &content, allow_republish)?;
+            let set_latest = !pkg_info.base.is_older_than_latest(version.as_str());
+            match (&pkg_info.base.latest_version, set_latest) {
+                (Some(latest), false) => log::info!(
+                    "{}: recording version {} without promoting it, it is older than the latest version {latest}",
+                    pkg_info.base.name,
+                    version.as_str()
+                ),
+                (Some(latest), true) if latest != version.as_str() => log::info!(
+                    "{}: moving the latest version from {latest} to {}",
+                    pkg_info.base.name,
+                    version.as_str()
+                ),
+                _ => (),
+            }
+            record_version(pkg_info, document, version.as_str(), content, set_latest)?;
+            let pruned = prune_versions(pkg_info, document)?;
+            if !pruned.is_empty() {
+                log::info!(
+                    "{}: pruned version(s) {}",
+                    pkg_info.base.name,
+                    pruned.join(", ")
+                );
+            }
+            Ok(())
+        })?;
+    }
+
+    report.downloaded_bytes = downloaded_bytes.into_inner();
+    report.timings = Timings {
+        resolve_ms: resolve_duration.as_millis() as u64,
+        total_ms: started_at.elapsed().as_millis() as u64,
+    };
+    Ok(report)
+}
+
+fn check_exit_code<'a>(
+    command: &Command,
+    reports: impl IntoIterator<Item = &'a PackageReport>,
+) -> ExitCode {
+    match command {
+        Command::Check => reports
+            .into_iter()
+            .map(|report| report.outcome.exit_code())
+            .max()
+            .map_or(ExitCode::SUCCESS, ExitCode::from),
+        _ => ExitCode::SUCCESS,
+    }
+}
+
+fn init(args: &Args, init_args: &InitArgs) -> anyhow::Result<()> {
+    let file = args.single_file()?;
+    anyhow::ensure!(
+        init_args.force || !file.exists(),
+        "{} already exist, use --force to overwrite it",
+        file.display()
+    );
+
+    let http_client = prepare_http_client_json()
+        .build()
+        .context("Failed to build HTTP client")?;
+    let download_client = prepare_http_client_download()
+        .build()
+        .context("Failed to build HTTP client")?;
+    let downloaded_bytes = AtomicU64::new(0);
+    let context = ModeContext {
+        tmp_dir: &args.tmp_dir,
+        in_test_mode: args.test,
+        http_client: &http_client,
+        download_client: &download_client,
+        downloaded_bytes: &downloaded_bytes,
+        github_api_url: args.github_api_url.as_ref(),
+        env_github_api_url: args.env_github_api_url.as_ref(),
+        github_token_files: &args.github_token_files,
+    };
 
     let tokio_runtime = tokio::runtime::Runtime::new()?;
-    let (version, content) = tokio_runtime.block_on(fut)?;
-    let mut pkg_info = pkg_info;
+    let (handler, option) =
+        tokio_runtime.block_on(GithubReleaseHandler::scaffold(&init_args.github, context))?;
+    log::info!("Guessed assets patterns: {:?}", handler.arch_asset_patterns);
 
-    let borrowed_version = Cow::Borrowed(version.as_str());
-    pkg_info.base.latest_version = Some(borrowed_version.clone());
-    let versions = pkg_info.base.versions.get_or_insert_with(Default::default);
-    *versions.entry(borrowed_version).or_default() = content;
+    let name = init_args.name.as_deref().unwrap_or_else(|| {
+        init_args
+            .github
+            .rsplit_once('/')
+            .map_or(init_args.github.as_str(), |(_, repo)| repo)
+    });
+    let pkg_info = PkgInfo {
+        base: PkgInfoBase {
+            schema: None,
+            name,
+            latest_version: None,
+            versions: None,
+            retention: None,
+        },
+        mode: PkgInfoMode::GithubRelease(handler),
+        option,
+    };
 
     let raw_dump_data = serde_json::to_string_pretty(&pkg_info).context("Serializing the data")?;
+    let problems = validate::Validator::new()?.validate(&raw_dump_data);
+    anyhow::ensure!(
+        problems.is_empty(),
+        "The generated pkg info is invalid:\n{}",
+        problems
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("\n")
+    );
+
+    dump_data_to_file(file, raw_dump_data.as_bytes())
+}
 
-    dump_data_to_file(&args.file, raw_dump_data.as_bytes())
+fn add_version(args: &Args, add_version_args: &AddVersionArgs) -> anyhow::Result<()> {
+    let file = args.single_file()?;
+    let raw_data = std::fs::read_to_string(file).context("Reading the data")?;
+    let pkg_info = serde_json::from_str::<PkgInfo>(&raw_data).context("Deserializing the data")?;
+
+    log::trace!("pkg_info={pkg_info:#?}");
+
+    let http_client = prepare_http_client_json()
+        .build()
+        .context("Failed to build HTTP client")?;
+    let download_client = prepare_http_client_download()
+        .build()
+        .context("Failed to build HTTP client")?;
+    let downloaded_bytes = AtomicU64::new(0);
+    let context = ModeContext {
+        tmp_dir: &args.tmp_dir,
+        in_test_mode: args.test,
+        http_client: &http_client,
+        download_client: &download_client,
+        downloaded_bytes: &downloaded_bytes,
+        github_api_url: args.github_api_url.as_ref(),
+        env_github_api_url: args.env_github_api_url.as_ref(),
+        github_token_files: &args.github_token_files,
+    };
+
+    let tokio_runtime = tokio::runtime::Runtime::new()?;
+    let (version, content) = tokio_runtime.block_on(pkg_info.mode.get_version(
+        &add_version_args.version,
+        &pkg_info.option,
+        context,
+    ))?;
+    // The requested version may be the upstream one, e.g. with the `v` prefix that is stripped.
+    let requested_version = Version::from_raw_str(
+        Cow::Borrowed(add_version_args.version.as_str()),
+        &pkg_info.option,
+    );
+    anyhow::ensure!(
+        version.as_str() == add_version_args.version
+            || requested_version.is_ok_and(|requested| requested.as_str() == version.as_str()),
+        "Requested version {} resolved to {}",
+        add_version_args.version,
+        version.as_str()
+    );
+    if let Some(constraint) = &pkg_info.option.version_constraint {
+        if !constraint.matches(version.as_str()) {
+            anyhow::ensure!(
+                add_version_args.ignore_version_constraint,
+                "{}: version {} does not satisfy the version constraint `{constraint}`, \
+                use `--ignore-version-constraint` to record it anyway",
+                pkg_info.base.name,
+                version.as_str()
+            );
+            log::warn!(
+                "{}: recording version {} outside the version constraint `{constraint}`",
+                pkg_info.base.name,
+                version.as_str()
+            );
+        }
+    }
+
+    modify_pkg_info(file, lock_pkg_info(file)?, |pkg_info, document| {
+        if add_version_args.set_latest && pkg_info.base.is_older_than_latest(version.as_str()) {
+            log::warn!(
+                "{}: setting the latest version to {} which is older than the current one",
+                pkg_info.base.name,
+                version.as_str()
+            );
+        }
+        log::info!(
+            "{}: recording version {} ({} asset(s))",
+            pkg_info.base.name,
+            version.as_str(),
+            content.len()
+        );
+
+        ensure_not_republished(pkg_info, version.as_str(), &content, args.allow_republish)?;
+        record_version(
+            pkg_info,
+            document,
+            version.as_str(),
+            content,
+            add_version_args.set_latest,
+        )
+    })
+}
+
+fn prune(args: &Args, prune_args: &PruneArgs) -> anyhow::Result<()> {
+    let files = batch::collect_files(&args.files)?;
+
+    for file in files {
+        let _lock = lock_pkg_info(&file)?;
+        let raw_data = std::fs::read_to_string(&file)
+            .with_context(|| format!("Reading the data of {}", file.display()))?;
+        let mut pkg_info = serde_json::from_str::<PkgInfo>(&raw_data)
+            .with_context(|| format!("Deserializing the data of {}", file.display()))?;
+        let mut document = JsonDocument::parse(raw_data.clone())?;
+
+        if pkg_info.base.retention.is_none() {
+            log::info!("{}: no retention policy", file.display());
+            continue;
+        }
+        let pruned = prune_versions(&mut pkg_info, &mut document)?;
+        if pruned.is_empty() {
+            log::info!("{}: nothing to prune", file.display());
+            continue;
+        }
+        println!("{}: pruned {}", file.display(), pruned.join(", "));
+        if !prune_args.dry_run {
+            write_pkg_info(&file, &document)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn sort_versions(args: &Args) -> anyhow::Result<()> {
+    let files = batch::collect_files(&args.files)?;
+
+    for file in files {
+        let _lock = lock_pkg_info(&file)?;
+        let raw_data = std::fs::read_to_string(&file)
+            .with_context(|| format!("Reading the data of {}", file.display()))?;
+        let mut document = JsonDocument::parse(raw_data)
+            .with_context(|| format!("Parsing the data of {}", file.display()))?;
+
+        if !document.is_object(&["versions"]) {
+            log::info!("{}: no recorded versions", file.display());
+            continue;
+        }
+        if document.sort_members(&["versions"], version::sort_cmp)? {
+            write_pkg_info(&file, &document)?;
+            println!("{}: sorted", file.display());
+        } else {
+            log::info!("{}: already sorted", file.display());
+        }
+    }
+
+    Ok(())
+}
+
+/// Refuse to overwrite the recorded assets of `version` if they changed upstream, unless `allow_republish`.
+fn ensure_not_republished(
+    pkg_info: &PkgInfo<'_>,
+    version: &str,
+    content: &VersionContent<'_>,
+    allow_republish: bool,
+) -> anyhow::Result<()> {
+    let republished = pkg_info.base.republished_assets(version, content);
+    if republished.is_empty() {
+        return Ok(());
+    }
+    let diff = republished
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    if allow_republish {
+        log::warn!(
+            "{} {version}: overwriting the re-published assets\n{diff}",
+            pkg_info.base.name
+        );
+        Ok(())
+    } else {
+        anyhow::bail!(
+            "{} {version}: the recorded assets were re-published upstream, \
+            use `--allow-republish` to overwrite them\n{diff}",
+            pkg_info.base.name
+        )
+    }
+}
+
+/// Record `version` in both the pkg info & its document, only the modified members are rewritten.
+fn record_version<'a>(
+    pkg_info: &mut PkgInfo<'a>,
+    document: &mut JsonDocument,
+    version: &str,
+    content: VersionContent<'static>,
+    set_latest: bool,
+) -> anyhow::Result<()> {
+    if set_latest {
+        document.set_member(&[], "latest_version", &version, Position::After("name"))?;
+    }
+    if !document.is_object(&["versions"]) {
+        document.set_member(
+            &[],
+            "versions",
+            &serde_json::Map::new(),
+            Position::After("latest_version"),
+        )?;
+    }
+    let predecessor = pkg_info
+        .base
+        .versions
+        .as_ref()
+        .and_then(|versions| versions.predecessor(version));
+    let position = predecessor.map_or(Position::First, Position::After);
+    document.set_member(&["versions"], version, &content, position)?;
+
+    let owned_version = Cow::<'a, str>::Owned(version.to_owned());
+    if set_latest {
+        pkg_info.base.latest_version = Some(owned_version.clone());
+    }
+    let versions = pkg_info.base.versions.get_or_insert_with(Default::default);
+    *versions.entry(owned_version).or_default() = content;
+    Ok(())
+}
+
+/// Apply the retention policy to both the pkg info & its document.
+fn prune_versions<'a>(
+    pkg_info: &mut PkgInfo<'a>,
+    document: &mut JsonDocument,
+) -> anyhow::Result<Vec<Cow<'a, str>>> {
+    let pruned = pkg_info.base.prune();
+    for version in &pruned {
+        document.remove_member(&["versions"], version)?;
+    }
+    Ok(pruned)
+}
+
+fn write_pkg_info(file: &Path, document: &JsonDocument) -> anyhow::Result<()> {
+    use std::io::Write;
+
+    write_atomic(file, |writer| {
+        writer.write_all(document.as_str().as_bytes())
+    })
+    .with_context(|| format!("Failed to write data to {}", file.display()))
+}
+
+fn print_check_summary(report: &PackageReport) {
+    println!("status={}", report.outcome);
+    println!("name={}", report.name);
+    if let Some(current_version) = &report.previous_version {
+        println!("current_version={current_version}");
+    }
+    println!("latest_version={}", report.new_version);
+}
+
+fn extract(pkg_info: &PkgInfo<'_>, args: &ExtractArgs) -> anyhow::Result<()> {
+    let info = ExtractedInfo::new(pkg_info, args.version.as_deref(), args.arch)?;
+
+    if args.json {
+        let raw_info = serde_json::to_string_pretty(&info).context("Serializing the info")?;
+        println!("{raw_info}");
+    } else {
+        print!("{info}");
+    }
+
+    if args.github_output {
+        use std::io::Write;
+
+        let output_path = std::env::var_os("GITHUB_OUTPUT")
+            .context("Cannot retrieve output file from env value `GITHUB_OUTPUT`")?;
+        let mut output_file = std::fs::OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(output_path)
+            .context("Cannot open github output file")?;
+        write!(output_file, "{info}").context("Failed to write to github output file")?;
+    }
+
+    Ok(())
+}
+
+fn pr_body(args: &Args, pr_body_args: &PrBodyArgs) -> anyhow::Result<()> {
+    let file = args.single_file()?;
+    let raw_new_data = std::fs::read_to_string(file).context("Reading the new data")?;
+    let new =
+        serde_json::from_str::<PkgInfo>(&raw_new_data).context("Deserializing the new data")?;
+    let raw_old_data =
+        std::fs::read_to_string(&pr_body_args.old).context("Reading the old data")?;
+    let old =
+        serde_json::from_str::<PkgInfo>(&raw_old_data).context("Deserializing the old data")?;
+
+    let release = match new.base.latest_version.as_deref() {
+        Some(version) => {
+            let http_client = prepare_http_client_json()
+                .build()
+                .context("Failed to build HTTP client")?;
+            let download_client = prepare_http_client_download()
+                .build()
+                .context("Failed to build HTTP client")?;
+            let downloaded_bytes = AtomicU64::new(0);
+            let context = ModeContext {
+                tmp_dir: &args.tmp_dir,
+                in_test_mode: args.test,
+                http_client: &http_client,
+                download_client: &download_client,
+                downloaded_bytes: &downloaded_bytes,
+                github_api_url: args.github_api_url.as_ref(),
+                env_github_api_url: args.env_github_api_url.as_ref(),
+                github_token_files: &args.github_token_files,
+            };
+            let tokio_runtime = tokio::runtime::Runtime::new()?;
+            tokio_runtime
+                .block_on(new.mode.get_release_notes(version, &new.option, context))
+                .unwrap_or_else(|e| {
+                    log::warn!("Cannot retrieve the release notes: {e:#}");
+                    None
+                })
+        }
+        None => None,
+    };
+
+    let body = PrBody::new(&old, &new, release)?;
+    match &pr_body_args.output {
+        Some(output) => dump_data_to_file(output, body.to_string().trim_end().as_bytes())
+            .context("Writing the pull-request body")?,
+        None => print!("{body}"),
+    }
+
+    Ok(())
+}
+
+fn download_asset(args: &Args, download_args: &DownloadArgs) -> anyhow::Result<()> {
+    let file = args.single_file()?;
+    let raw_data = std::fs::read_to_string(file).context("Reading the data")?;
+    let pkg_info = serde_json::from_str::<PkgInfo>(&raw_data).context("Deserializing the data")?;
+
+    let arch = match download_args.arch {
+        Some(arch) => arch,
+        None => download::detect_arch()?,
+    };
+    let (version, entry) =
+        pkg_info_updater::extract::find_entry(&pkg_info, download_args.version.as_deref(), arch)?;
+    let destination = download_args.output.clone().unwrap_or_else(|| {
+        download::default_destination(&entry.filename, download_args.extract.as_deref())
+    });
+
+    let http_client = prepare_http_client_download()
+        .build()
+        .context("Failed to build HTTP client")?;
+    let tokio_runtime = tokio::runtime::Runtime::new()?;
+
+    log::info!(
+        "{} {version} {arch}: downloading {} ...",
+        pkg_info.base.name,
+        entry.download_url
+    );
+    match &download_args.extract {
+        Some(member) => {
+            let kind = ArchiveKind::from_filename(&entry.filename).with_context(|| {
+                format!("Unsupported archive format for asset {}", entry.filename)
+            })?;
+            let archive_path = args.tmp_dir.join(entry.filename.as_ref());
+            tokio_runtime.block_on(download::download_entry(&http_client, entry, &archive_path))?;
+            let res = download::extract_member(&archive_path, kind, member, &destination);
+            let _ = std::fs::remove_file(&archive_path);
+            res?;
+        }
+        None => {
+            tokio_runtime.block_on(download::download_entry(&http_client, entry, &destination))?;
+        }
+    }
+    log::info!(
+        "{} {version} {arch}: verified & placed at {}",
+        pkg_info.base.name,
+        destination.display()
+    );
+
+    Ok(())
+}
+
+fn verify(args: &Args, verify_args: &VerifyArgs) -> anyhow::Result<()> {
+    use futures::StreamExt;
+
+    let files = batch::collect_files(&args.files)?;
+    let http_client = prepare_http_client_download()
+        .build()
+        .context("Failed to build HTTP client")?;
+    let tokio_runtime = tokio::runtime::Runtime::new()?;
+
+    let mut checked = 0;
+    let mut failed = 0;
+    for file in files {
+        let raw_data = std::fs::read_to_string(&file)
+            .with_context(|| format!("Reading the data of {}", file.display()))?;
+        let pkg_info = serde_json::from_str::<PkgInfo>(&raw_data)
+            .with_context(|| format!("Deserializing the data of {}", file.display()))?;
+        let targets =
+            verify::select_targets(&pkg_info, &verify_args.versions, &verify_args.arches)?;
+
+        log::info!(
+            "{}: verifying {} asset(s) ...",
+            pkg_info.base.name,
+            targets.len()
+        );
+        let results = tokio_runtime.block_on(
+            futures::stream::iter(&targets)
+                .map(|target| verify::verify_entry(&http_client, target.entry))
+                .buffered(args.jobs.get())
+                .collect::<Vec<_>>(),
+        );
+
+        for (target, status) in targets.iter().zip(results) {
+            checked += 1;
+            if !status.is_ok() {
+                failed += 1;
+            }
+            println!(
+                "{} {} {}: {status}",
+                pkg_info.base.name, target.version, target.arch
+            );
+        }
+    }
+
+    anyhow::ensure!(
+        failed == 0,
+        "{failed} of {checked} asset(s) failed the verification"
+    );
+
+    Ok(())
+}
+
+fn validate(args: &Args) -> anyhow::Result<()> {
+    let files = batch::collect_files(&args.files)?;
+    let validator = validate::Validator::new()?;
+
+    let mut invalid_files = 0;
+    for file in &files {
+        let raw_data = std::fs::read_to_string(file)
+            .with_context(|| format!("Reading the data of {}", file.display()))?;
+        let problems = validator.validate(&raw_data);
+
+        if problems.is_empty() {
+            log::info!("{}: valid", file.display());
+        } else {
+            invalid_files += 1;
+        }
+        for problem in problems {
+            println!("{}:{problem}", file.display());
+        }
+    }
+
+    anyhow::ensure!(
+        invalid_files == 0,
+        "{invalid_files} of {} file(s) are invalid",
+        files.len()
+    );
+
+    Ok(())
 }
 
 fn init_log() {
@@ -57,18 +959,42 @@ fn init_log() {
     Builder::from_env(Env::default().default_filter_or(DEFAULT_FILTER)).init();
 }
 
-fn dump_data_to_file(file: &Path, data: &[u8]) -> anyhow::Result<()> {
-    use std::{
-        fs::File,
-        io::{BufWriter, IoSlice, Write},
-    };
+/// Prevent concurrent runs from modifying the pkg info file at the same time.
+fn lock_pkg_info(file: &Path) -> anyhow::Result<FileLock> {
+    FileLock::acquire(file).with_context(|| format!("Cannot lock {}", file.display()))
+}
 
-    let file = File::create(file).context("Cannot open pkg info file")?;
-    let mut buf_writer = BufWriter::new(file);
+/// Wait for the lock of the pkg info file on a blocking thread, the other packages keep progressing.
+async fn lock_pkg_info_async(file: &Path) -> anyhow::Result<FileLock> {
+    let file = file.to_owned();
+    tokio::task::spawn_blocking(move || lock_pkg_info(&file)).await?
+}
 
-    buf_writer
-        .write_vectored(&[IoSlice::new(data), IoSlice::new(b"\n")])
-        .context("Failed to write data to file")?;
+/// Apply `modify` to the pkg info file re-read under its `lock`, then write it.
+///
+/// The file is re-read so the changes made by another run while resolving the version are kept.
+fn modify_pkg_info(
+    file: &Path,
+    lock: FileLock,
+    modify: impl FnOnce(&mut PkgInfo<'_>, &mut JsonDocument) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    let raw_data = std::fs::read_to_string(file).context("Reading the data")?;
+    let mut pkg_info =
+        serde_json::from_str::<PkgInfo>(&raw_data).context("Deserializing the data")?;
+    let mut document = JsonDocument::parse(raw_data.clone())?;
 
+    modify(&mut pkg_info, &mut document)?;
+    write_pkg_info(file, &document)?;
+    drop(lock);
     Ok(())
 }
+
+fn dump_data_to_file(file: &Path, data: &[u8]) -> anyhow::Result<()> {
+    use std::io::Write;
+
+    write_atomic(file, |writer| {
+        writer.write_all(data)?;
+        writer.write_all(b"\n")
+    })
+    .with_context(|| format!("Failed to write data to {}", file.display()))
+}
@@ -1,20 +1,38 @@
 mod mode;
+mod retention;
 
 use std::{
     borrow::Cow,
     collections::HashMap,
     fmt::{Debug, Display, Write},
     ops::{Deref, DerefMut},
+    str::FromStr,
 };
 
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use url::Url;
 
+use crate::version::{self, VersionConstraint, VersionTransform};
+
 pub use mode::{
-    BashCmdReleaseHandler, GithubReleaseHandler, JqScriptReleaseHandler, Mode, ModeGetLatestVersion,
+    BashCmdReleaseHandler, GithubReleaseHandler, GithubTokenFile, GithubVersionSource,
+    JqScriptReleaseHandler, MissingArchPolicy, Mode, ModeContext, ModeGetLatestVersion,
+    ReleaseField, ReleaseNotes, VersionRegex,
 };
+pub use retention::Retention;
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+/// A package information descriptor.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[schemars(
+    title = "pkg-info",
+    extend(
+        "patternProperties" = {
+            "^x-": { "description": "An extension member, left untouched by the updater." }
+        },
+        "unevaluatedProperties" = false
+    )
+)]
 pub struct PkgInfo<'a> {
     #[serde(flatten, borrow)]
     pub base: Base<'a>,
@@ -24,31 +42,252 @@ pub struct PkgInfo<'a> {
     pub option: PkgOption,
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Default, Clone, Copy)]
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Default, Clone, JsonSchema)]
 pub struct PkgOption {
-    /// Remove the `v` prefix from the version string.
+    /// Remove the `v` prefix from the version string, applied before `version_transform`.
     #[serde(default)]
     pub strip_v_prefix: bool,
-    /// Allow to use prerelease version.
+    /// The steps applied in order to the version provided by the mode.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub version_transform: Vec<VersionTransform>,
+    /// Allow to use prerelease version, a shorthand for the `include` prerelease policy.
     #[serde(default)]
     pub allow_prerelease: bool,
+    /// Whether prereleases are considered when looking for the latest version.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prerelease_policy: Option<PrereleasePolicy>,
+    /// Restrict the versions to a range (e.g. to stay on a major version).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version_constraint: Option<VersionConstraint>,
+}
+
+impl PkgOption {
+    /// The prerelease policy in effect, `prerelease_policy` takes precedence over `allow_prerelease`.
+    pub fn prerelease_policy(&self) -> PrereleasePolicy {
+        match self.prerelease_policy {
+            Some(policy) => policy,
+            None if self.allow_prerelease => PrereleasePolicy::Include,
+            None => PrereleasePolicy::StableOnly,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Default, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum PrereleasePolicy {
+    /// Only consider stable releases.
+    #[default]
+    StableOnly,
+    /// Consider the most recent release, stable or prerelease.
+    Include,
+    /// Consider the most recent stable release, falling back to a prerelease when there are none.
+    PreferStable,
+}
+
+impl PrereleasePolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PrereleasePolicy::StableOnly => "stable-only",
+            PrereleasePolicy::Include => "include",
+            PrereleasePolicy::PreferStable => "prefer-stable",
+        }
+    }
+}
+
+impl Display for PrereleasePolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
 pub struct Base<'a> {
     #[serde(borrow, rename = "$schema", skip_serializing_if = "Option::is_none")]
     pub schema: Option<&'a str>,
+    /// The name of the package.
     #[serde(borrow)]
     pub name: &'a str,
+    /// The latest known version of the package.
     #[serde(borrow, skip_serializing_if = "Option::is_none")]
     pub latest_version: Option<Cow<'a, str>>,
+    /// The recorded versions of the package.
     #[serde(borrow, skip_serializing_if = "Option::is_none")]
     pub versions: Option<Versions<'a>>,
+    /// The policy used to prune the recorded versions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retention: Option<Retention>,
+}
+
+impl<'a> Base<'a> {
+    /// Compare the provided version & its content with what is currently recorded.
+    ///
+    /// An already recorded version older than `latest_version` is compared by its content.
+    pub fn check_version(&self, version: &str, content: &VersionContent<'_>) -> CheckOutcome {
+        let recorded_content = self
+            .versions
+            .as_ref()
+            .and_then(|versions| versions.get(version));
+        if self.latest_version.as_deref() != Some(version)
+            && !(self.is_older_than_latest(version) && recorded_content.is_some())
+        {
+            return CheckOutcome::NewVersion;
+        }
+        if recorded_content.is_some_and(|recorded| recorded == content) {
+            CheckOutcome::UpToDate
+        } else {
+            CheckOutcome::AssetsChanged
+        }
+    }
+
+    /// Check if `version` precede `latest_version`, in which case it should not replace it.
+    pub fn is_older_than_latest(&self, version: &str) -> bool {
+        self.latest_version
+            .as_deref()
+            .is_some_and(|latest| version::compare(version, latest).is_lt())
+    }
+
+    /// The recorded assets of `version` that differ by their filename, digest or URL from the provided ones,
+    /// or that were added or removed.
+    ///
+    /// Upstream re-publishing the assets of a released version is suspicious, it should not go unnoticed.
+    pub fn republished_assets(
+        &self,
+        version: &str,
+        content: &VersionContent<'_>,
+    ) -> Vec<RepublishedAsset> {
+        let Some(recorded) = self
+            .versions
+            .as_ref()
+            .and_then(|versions| versions.get(version))
+        else {
+            return Vec::new();
+        };
+
+        Arch::ALL
+            .into_iter()
+            .filter_map(|arch| {
+                let (previous, current) = (recorded.get(&arch), content.get(&arch));
+                let changed = match (previous, current) {
+                    (Some(previous), Some(current)) => {
+                        previous.filename != current.filename
+                            || previous.digest != current.digest
+                            || previous.download_url != current.download_url
+                    }
+                    (None, None) => false,
+                    _ => true,
+                };
+                changed.then(|| RepublishedAsset {
+                    arch,
+                    previous: previous.map(VersionedArchEntry::to_owned),
+                    current: current.map(VersionedArchEntry::to_owned),
+                })
+            })
+            .collect()
+    }
+
+    /// Remove the recorded versions not kept by the retention policy.
+    ///
+    /// Return the removed versions, from the oldest to the most recent.
+    pub fn prune(&mut self) -> Vec<Cow<'a, str>> {
+        let (Some(retention), Some(versions)) = (&self.retention, &mut self.versions) else {
+            return Vec::new();
+        };
+        let pruned = retention
+            .select_pruned(
+                versions.keys().map(AsRef::as_ref),
+                self.latest_version.as_deref(),
+            )
+            .into_iter()
+            .map(ToOwned::to_owned)
+            .collect::<Vec<String>>();
+
+        pruned
+            .into_iter()
+            .filter_map(|version| versions.remove_entry(version.as_str()))
+            .map(|(version, _)| version)
+            .collect()
+    }
+}
+
+/// A recorded asset re-published upstream with a different filename, digest or URL,
+/// `None` on the side the asset is missing from when it was added or removed.
+#[derive(Debug, PartialEq, Eq)]
+pub struct RepublishedAsset {
+    pub arch: Arch,
+    pub previous: Option<VersionedArchEntry<'static>>,
+    pub current: Option<VersionedArchEntry<'static>>,
+}
+
+impl Display for RepublishedAsset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (previous, current) = match (&self.previous, &self.current) {
+            (Some(previous), Some(current)) => (previous, current),
+            (Some(previous), None) => {
+                return write!(f, "{}: removed ({})", self.arch, previous.filename)
+            }
+            (None, Some(current)) => {
+                return write!(f, "{}: added ({})", self.arch, current.filename)
+            }
+            (None, None) => return write!(f, "{}:", self.arch),
+        };
+        write!(f, "{}:", self.arch)?;
+        if previous.filename != current.filename {
+            write!(
+                f,
+                "\n  filename: {} -> {}",
+                previous.filename, current.filename
+            )?;
+        }
+        if previous.download_url != current.download_url {
+            write!(
+                f,
+                "\n  download_url: {} -> {}",
+                previous.download_url, current.download_url
+            )?;
+        }
+        if previous.digest != current.digest {
+            write!(f, "\n  digest: {} -> {}", previous.digest, current.digest)?;
+        }
+        Ok(())
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize, Default, PartialEq, Eq)]
+/// The outcome of comparing the latest version with the recorded one.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CheckOutcome {
+    /// The latest version is already recorded with the same assets.
+    UpToDate,
+    /// The latest version differs from the recorded one.
+    NewVersion,
+    /// The latest version is the recorded one but its assets differ.
+    AssetsChanged,
+}
+
+impl CheckOutcome {
+    /// The process exit code associated to the outcome.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            CheckOutcome::UpToDate => 0,
+            CheckOutcome::NewVersion => 10,
+            CheckOutcome::AssetsChanged => 11,
+        }
+    }
+}
+
+impl Display for CheckOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            CheckOutcome::UpToDate => "up-to-date",
+            CheckOutcome::NewVersion => "new-version",
+            CheckOutcome::AssetsChanged => "assets-changed",
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, PartialEq, Eq, JsonSchema)]
 pub struct Versions<'a>(
-    #[serde(borrow, serialize_with = "crate::serde_utils::ordered_map")]
+    #[serde(borrow, serialize_with = "crate::serde_utils::version_ordered_map")]
     HashMap<Cow<'a, str>, VersionContent<'a>>,
 );
 
@@ -66,13 +305,23 @@ impl<'a> DerefMut for Versions<'a> {
     }
 }
 
+impl Versions<'_> {
+    /// The recorded version preceding `version` in version order.
+    pub fn predecessor(&self, version: &str) -> Option<&str> {
+        self.keys()
+            .map(AsRef::as_ref)
+            .filter(|recorded| version::sort_cmp(recorded, version).is_lt())
+            .max_by(|a, b| version::sort_cmp(a, b))
+    }
+}
+
 impl<'a> FromIterator<(Cow<'a, str>, VersionContent<'a>)> for Versions<'a> {
     fn from_iter<T: IntoIterator<Item = (Cow<'a, str>, VersionContent<'a>)>>(iter: T) -> Self {
         Self(HashMap::from_iter(iter))
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[derive(Debug, Serialize, Deserialize, Default, PartialEq, Eq, JsonSchema)]
 pub struct VersionContent<'a>(
     #[serde(borrow, serialize_with = "crate::serde_utils::ordered_map")]
     HashMap<Arch, VersionedArchEntry<'a>>,
@@ -92,14 +341,49 @@ impl<'a> Deref for VersionContent<'a> {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Hash, PartialOrd, Ord)]
+#[derive(
+    Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Hash, PartialOrd, Ord, JsonSchema,
+)]
 #[serde(rename_all = "lowercase")]
 pub enum Arch {
     Amd64,
     Arm64,
 }
 
-#[derive(Serialize, Deserialize, PartialEq, Eq)]
+impl Arch {
+    pub const ALL: [Arch; 2] = [Arch::Amd64, Arch::Arm64];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Arch::Amd64 => "amd64",
+            Arch::Arm64 => "arm64",
+        }
+    }
+}
+
+impl Display for Arch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Arch {
+    type Err = ParseArchError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|arch| arch.as_str() == s)
+            .ok_or_else(|| ParseArchError(s.to_owned()))
+    }
+}
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+#[error("Unknown arch `{}`, expected one of amd64, arm64", .0)]
+pub struct ParseArchError(String);
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct VersionedArchEntry<'a> {
     #[serde(borrow)]
     pub filename: Cow<'a, str>,
@@ -108,6 +392,16 @@ pub struct VersionedArchEntry<'a> {
     pub digest: Digest<'a>,
 }
 
+impl<'a> VersionedArchEntry<'a> {
+    pub fn to_owned(&self) -> VersionedArchEntry<'static> {
+        VersionedArchEntry {
+            filename: Cow::Owned(self.filename.to_string()),
+            download_url: self.download_url.clone(),
+            digest: self.digest.to_owned(),
+        }
+    }
+}
+
 impl<'a> Debug for VersionedArchEntry<'a> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("VersionedArchEntry")
@@ -134,6 +428,21 @@ impl<'a> Digest<'a> {
             Digest::Sha256(v) => Digest::Sha256(Cow::Owned(v.to_string())),
         }
     }
+
+    /// The algorithm used to compute the digest.
+    pub fn algorithm(&self) -> DigestAlgorithm {
+        match self {
+            Digest::Sha512(_) => DigestAlgorithm::Sha512,
+            Digest::Sha256(_) => DigestAlgorithm::Sha256,
+        }
+    }
+
+    /// The hex encoded digest value (without the algorithm prefix).
+    pub fn value(&self) -> &str {
+        match self {
+            Digest::Sha512(digest) | Digest::Sha256(digest) => digest,
+        }
+    }
 }
 
 impl<'a> TryFrom<&'a str> for Digest<'a> {
@@ -172,13 +481,26 @@ impl<'a> TryFrom<&'a str> for Digest<'a> {
 
 impl<'a> Display for Digest<'a> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let (id, digest) = match self {
-            Digest::Sha512(digest) => ("sha512", digest),
-            Digest::Sha256(digest) => ("sha256", digest),
-        };
-        f.write_str(id)?;
+        f.write_str(self.algorithm().as_str())?;
         f.write_char(':')?;
-        f.write_str(digest)
+        f.write_str(self.value())
+    }
+}
+
+impl JsonSchema for Digest<'_> {
+    fn schema_name() -> Cow<'static, str> {
+        Cow::Borrowed("Digest")
+    }
+
+    fn json_schema(_generator: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        schemars::json_schema!({
+            "description": "The digest of the asset prefixed by the algorithm used.",
+            "type": "string",
+            "oneOf": [
+                { "pattern": "^sha256:[0-9a-f]{64}$" },
+                { "pattern": "^sha512:[0-9a-f]{128}$" }
+            ]
+        })
     }
 }
 
@@ -191,6 +513,77 @@ impl<'a> Serialize for Digest<'a> {
     }
 }
 
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum DigestAlgorithm {
+    Sha512,
+    Sha256,
+}
+
+impl DigestAlgorithm {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DigestAlgorithm::Sha512 => "sha512",
+            DigestAlgorithm::Sha256 => "sha256",
+        }
+    }
+
+    /// Create a new hasher for the algorithm.
+    pub fn hasher(&self) -> DigestHasher {
+        use sha2::Digest as _;
+
+        match self {
+            DigestAlgorithm::Sha512 => DigestHasher::Sha512(sha2::Sha512::new()),
+            DigestAlgorithm::Sha256 => DigestHasher::Sha256(sha2::Sha256::new()),
+        }
+    }
+}
+
+impl Display for DigestAlgorithm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// An incremental hasher producing a [`Digest`].
+pub enum DigestHasher {
+    Sha512(sha2::Sha512),
+    Sha256(sha2::Sha256),
+}
+
+impl DigestHasher {
+    pub fn update(&mut self, data: &[u8]) {
+        use sha2::Digest as _;
+
+        match self {
+            DigestHasher::Sha512(hasher) => hasher.update(data),
+            DigestHasher::Sha256(hasher) => hasher.update(data),
+        }
+    }
+
+    pub fn finalize(self) -> Digest<'static> {
+        use sha2::Digest as _;
+
+        match self {
+            DigestHasher::Sha512(hasher) => {
+                Digest::Sha512(Cow::Owned(bytes_to_hex_str(&hasher.finalize())))
+            }
+            DigestHasher::Sha256(hasher) => {
+                Digest::Sha256(Cow::Owned(bytes_to_hex_str(&hasher.finalize())))
+            }
+        }
+    }
+}
+
+fn bytes_to_hex_str(bytes: &[u8]) -> String {
+    let mut res = String::with_capacity(bytes.len() * 2);
+
+    bytes
+        .iter()
+        .for_each(|byte| write!(&mut res, "{byte:02x}").unwrap());
+
+    res
+}
+
 #[derive(Debug, thiserror::Error, PartialEq, Eq)]
 pub enum ParseDigestError<'a> {
     #[error("Invalid digest size, expected {} but got {}", .expected, .got)]
@@ -230,4 +623,185 @@ mod tests {
             assert_eq!(digest.to_string(), input);
         }
     }
+
+    #[rstest]
+    #[case::sha256(
+        DigestAlgorithm::Sha256,
+        "sha256:2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+    )]
+    #[case::sha512(
+        DigestAlgorithm::Sha512,
+        "sha512:9b71d224bd62f3785d96d46ad3ea3d73319bfbc2890caadae2dff72519673ca72323c3d99ba5c11d7c7acc6e14b8c5da0c4663475c2e5c3adef46f73bcdec043"
+    )]
+    fn test_digest_hasher(#[case] algorithm: DigestAlgorithm, #[case] expected: &str) {
+        let mut hasher = algorithm.hasher();
+        hasher.update(b"hel");
+        hasher.update(b"lo");
+
+        assert_eq!(hasher.finalize().to_string(), expected);
+    }
+
+    fn version_content(digest: &'static str) -> VersionContent<'static> {
+        [(
+            Arch::Amd64,
+            VersionedArchEntry {
+                filename: Cow::Borrowed("foo"),
+                download_url: "https://example.com/foo".parse().unwrap(),
+                digest: Digest::Sha256(Cow::Borrowed(digest)),
+            },
+        )]
+        .into_iter()
+        .collect()
+    }
+
+    #[rstest]
+    #[case::up_to_date("1.0.0", "aa", CheckOutcome::UpToDate)]
+    #[case::new_version("1.1.0", "aa", CheckOutcome::NewVersion)]
+    #[case::assets_changed("1.0.0", "bb", CheckOutcome::AssetsChanged)]
+    fn test_check_version(
+        #[case] version: &str,
+        #[case] digest: &'static str,
+        #[case] expected: CheckOutcome,
+    ) {
+        let base = Base {
+            schema: None,
+            name: "foo",
+            latest_version: Some(Cow::Borrowed("1.0.0")),
+            versions: Some(
+                [(Cow::Borrowed("1.0.0"), version_content("aa"))]
+                    .into_iter()
+                    .collect(),
+            ),
+            retention: None,
+        };
+
+        assert_eq!(
+            base.check_version(version, &version_content(digest)),
+            expected
+        );
+    }
+
+    /// The content with an asset per `(arch, filename, digest)`.
+    fn content_of(assets: &[(Arch, &'static str, &'static str)]) -> VersionContent<'static> {
+        assets
+            .iter()
+            .map(|&(arch, filename, digest)| {
+                (
+                    arch,
+                    VersionedArchEntry {
+                        filename: Cow::Borrowed(filename),
+                        download_url: format!("https://example.com/{filename}").parse().unwrap(),
+                        digest: Digest::Sha256(Cow::Borrowed(digest)),
+                    },
+                )
+            })
+            .collect()
+    }
+
+    #[rstest]
+    #[case::unknown_version("1.1.0", &[(Arch::Amd64, "foo", "bb")], &[])]
+    #[case::same_assets("1.0.0", &[(Arch::Amd64, "foo", "aa")], &[])]
+    #[case::digest_changed(
+        "1.0.0",
+        &[(Arch::Amd64, "foo", "bb")],
+        &["amd64:\n  digest: sha256:aa -> sha256:bb"]
+    )]
+    #[case::filename_changed(
+        "1.0.0",
+        &[(Arch::Amd64, "bar", "aa")],
+        &["amd64:\n  filename: foo -> bar\n  download_url: https://example.com/foo -> https://example.com/bar"]
+    )]
+    #[case::arch_removed("1.0.0", &[], &["amd64: removed (foo)"])]
+    #[case::arch_added(
+        "1.0.0",
+        &[(Arch::Amd64, "foo", "aa"), (Arch::Arm64, "foo-arm64", "cc")],
+        &["arm64: added (foo-arm64)"]
+    )]
+    fn test_republished_assets(
+        #[case] version: &str,
+        #[case] assets: &[(Arch, &'static str, &'static str)],
+        #[case] expected: &[&str],
+    ) {
+        let base = Base {
+            schema: None,
+            name: "foo",
+            latest_version: Some(Cow::Borrowed("1.0.0")),
+            versions: Some(
+                [(Cow::Borrowed("1.0.0"), version_content("aa"))]
+                    .into_iter()
+                    .collect(),
+            ),
+            retention: None,
+        };
+
+        let republished = base.republished_assets(version, &content_of(assets));
+
+        assert_eq!(
+            republished
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            expected
+        );
+    }
+
+    #[rstest]
+    #[case::recorded("0.9.0", "aa", CheckOutcome::UpToDate)]
+    #[case::recorded_changed("0.9.0", "bb", CheckOutcome::AssetsChanged)]
+    #[case::not_recorded("0.8.0", "aa", CheckOutcome::NewVersion)]
+    fn test_check_older_version(
+        #[case] version: &str,
+        #[case] digest: &'static str,
+        #[case] expected: CheckOutcome,
+    ) {
+        let base = Base {
+            schema: None,
+            name: "foo",
+            latest_version: Some(Cow::Borrowed("1.0.0")),
+            versions: Some(
+                [
+                    (Cow::Borrowed("0.9.0"), version_content("aa")),
+                    (Cow::Borrowed("1.0.0"), version_content("aa")),
+                ]
+                .into_iter()
+                .collect(),
+            ),
+            retention: None,
+        };
+
+        assert_eq!(
+            base.check_version(version, &version_content(digest)),
+            expected
+        );
+    }
+
+    #[rstest]
+    #[case::first("0.9.0", None)]
+    #[case::between("1.5.0", Some("1.0.0"))]
+    #[case::numeric_order("10.0.0", Some("9.0.0"))]
+    #[case::prerelease("9.0.0-rc.1", Some("1.0.0"))]
+    fn test_versions_predecessor(#[case] version: &str, #[case] expected: Option<&str>) {
+        let versions = ["1.0.0", "9.0.0"]
+            .into_iter()
+            .map(|version| (Cow::Borrowed(version), version_content("aa")))
+            .collect::<Versions>();
+
+        assert_eq!(versions.predecessor(version), expected);
+    }
+
+    #[test]
+    fn test_check_version_missing_entry() {
+        let base = Base {
+            schema: None,
+            name: "foo",
+            latest_version: Some(Cow::Borrowed("1.0.0")),
+            versions: None,
+            retention: None,
+        };
+
+        assert_eq!(
+            base.check_version("1.0.0", &version_content("aa")),
+            CheckOutcome::AssetsChanged
+        );
+    }
 }
@@ -3,15 +3,22 @@ mod external_cmd;
 mod github;
 mod jq_script;
 
-use std::path::Path;
+use std::{
+    path::Path,
+    sync::atomic::{AtomicU64, Ordering},
+};
 
 use futures::Future;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use super::VersionContent;
 
 pub use bash_command::ReleaseHandler as BashCmdReleaseHandler;
-pub use github::ReleaseHandler as GithubReleaseHandler;
+pub use github::{
+    GithubTokenFile, MissingArchPolicy, ReleaseField, ReleaseHandler as GithubReleaseHandler,
+    VersionRegex, VersionSource as GithubVersionSource,
+};
 pub use jq_script::ReleaseHandler as JqScriptReleaseHandler;
 
 use crate::{
@@ -19,46 +26,118 @@ use crate::{
     PkgOption,
 };
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+/// The shared resources used by a mode to retrieve a version.
+#[derive(Debug, Clone, Copy)]
+pub struct ModeContext<'a> {
+    /// Path to a temporary folder.
+    pub tmp_dir: &'a Path,
+    /// Test mode is enabled (do not require a clean working env).
+    pub in_test_mode: bool,
+    /// The HTTP client shared between the packages.
+    pub http_client: &'a reqwest::Client,
+    /// The HTTP client computing the digest of the assets, the same `download` & `verify` use.
+    pub download_client: &'a reqwest::Client,
+    /// The number of bytes downloaded to retrieve the version (assets & documents).
+    pub downloaded_bytes: &'a AtomicU64,
+    /// The GitHub API to use instead of the one configured by the pkg info.
+    pub github_api_url: Option<&'a url::Url>,
+    /// The GitHub API to use when the pkg info configures none (`GITHUB_API_URL`).
+    pub env_github_api_url: Option<&'a url::Url>,
+    /// The files containing the token of a GitHub host.
+    pub github_token_files: &'a [GithubTokenFile],
+}
+
+impl ModeContext<'_> {
+    /// Account for `bytes` downloaded while retrieving the version.
+    pub fn record_download(&self, bytes: u64) {
+        self.downloaded_bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
 #[serde(rename_all = "kebab-case", tag = "mode")]
+#[schemars(transform = crate::schema::tag_conditions)]
 pub enum Mode<'a> {
     GithubRelease(#[serde(borrow)] github::ReleaseHandler<'a>),
     BashCommand(#[serde(borrow)] bash_command::ReleaseHandler<'a>),
     JqScript(#[serde(borrow)] jq_script::ReleaseHandler<'a>),
 }
 
+/// The upstream release of a version.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReleaseNotes {
+    /// The URL of the release page.
+    pub url: url::Url,
+    /// The release notes (Markdown).
+    pub body: Option<String>,
+}
+
 pub type VersionComponent = (RawVersion<'static>, VersionContent<'static>);
 
 impl<'a> Mode<'a> {
+    /// The name of the mode as written in the pkg info file.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Mode::GithubRelease(_) => "github-release",
+            Mode::BashCommand(_) => "bash-command",
+            Mode::JqScript(_) => "jq-script",
+        }
+    }
+
+    /// Retrieve the latest version and its assets.
     pub async fn get_latest_version(
         &self,
         option: &PkgOption,
-        tmp_dir: &Path,
-        in_test_mode: bool,
+        context: ModeContext<'_>,
     ) -> anyhow::Result<(Version<'static>, VersionContent<'static>)> {
         match self {
-            Mode::GithubRelease(gh_release) => {
-                gh_release
-                    .get_latest_version(option, tmp_dir, in_test_mode)
-                    .await
+            Mode::GithubRelease(gh_release) => gh_release.get_latest_version(option, context).await,
+            Mode::BashCommand(command) => command.get_latest_version(option, context).await,
+            Mode::JqScript(script) => script.get_latest_version(option, context).await,
+        }
+        .and_then(|(raw_version, content)| {
+            let version = Version::from_raw(raw_version, option)?;
+            if let Some(constraint) = &option.version_constraint {
+                anyhow::ensure!(
+                    constraint.matches(version.as_str()),
+                    "Version {} does not satisfy the version constraint `{constraint}`",
+                    version.as_str()
+                );
             }
-            Mode::BashCommand(command) => {
-                command
-                    .get_latest_version(option, tmp_dir, in_test_mode)
-                    .await
+            Ok((version, content))
+        })
+    }
+
+    /// Retrieve the assets of the requested upstream `version`.
+    pub async fn get_version(
+        &self,
+        version: &str,
+        option: &PkgOption,
+        context: ModeContext<'_>,
+    ) -> anyhow::Result<(Version<'static>, VersionContent<'static>)> {
+        match self {
+            Mode::GithubRelease(gh_release) => {
+                gh_release.get_version(version, option, context).await
             }
-            Mode::JqScript(script) => {
-                script
-                    .get_latest_version(option, tmp_dir, in_test_mode)
-                    .await
+            Mode::BashCommand(command) => command.get_version(version, option, context).await,
+            Mode::JqScript(script) => script.get_version(version, option, context).await,
+        }
+        .and_then(|(raw_version, content)| Ok((Version::from_raw(raw_version, option)?, content)))
+    }
+
+    /// Retrieve the upstream release page & notes of `version`, `None` when the mode cannot provide them.
+    pub async fn get_release_notes(
+        &self,
+        version: &str,
+        option: &PkgOption,
+        context: ModeContext<'_>,
+    ) -> anyhow::Result<Option<ReleaseNotes>> {
+        match self {
+            Mode::GithubRelease(gh_release) => {
+                gh_release.get_release_notes(version, option, context).await
             }
+            Mode::BashCommand(_) | Mode::JqScript(_) => Ok(None),
         }
-        .map(|(raw_version, content)| {
-            (
-                Version::from_raw(raw_version, option.strip_v_prefix),
-                content,
-            )
-        })
     }
 }
 
@@ -66,7 +145,13 @@ pub trait ModeGetLatestVersion {
     fn get_latest_version(
         &self,
         option: &PkgOption,
-        tmp_dir: &Path,
-        in_test_mode: bool,
+        context: ModeContext<'_>,
+    ) -> impl Future<Output = anyhow::Result<VersionComponent>>;
+
+    fn get_version(
+        &self,
+        version: &str,
+        option: &PkgOption,
+        context: ModeContext<'_>,
     ) -> impl Future<Output = anyhow::Result<VersionComponent>>;
 }
@@ -2,24 +2,31 @@
 //!
 //! The command will be provided with those environment variable:
 //!
-//! | Name      | Description                         |
-//! | --------- | ----------------------------------- |
-//! | `TEST`    | The script is executed in test mode |
-//! | `TMP_DIR` | Path to a temporary folder          |
+//! | Name                 | Description                                   |
+//! | -------------------- | --------------------------------------------- |
+//! | `TEST`               | The script is executed in test mode           |
+//! | `TMP_DIR`            | Path to a temporary folder                    |
+//! | `ALLOW_PRERELEASE`   | Set to `1` when prereleases are allowed       |
+//! | `PRERELEASE_POLICY`  | The prerelease policy in effect               |
+//! | `REQUESTED_VERSION`  | The version to retrieve instead of the latest |
+//! | `VERSION_CONSTRAINT` | The range the version must be in              |
 
-use std::{borrow::Cow, ffi::OsStr, path::Path, process::Stdio};
+use std::{borrow::Cow, ffi::OsStr, process::Stdio};
 
 use anyhow::Context;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use tokio::process::Command;
 
-use crate::{ModeGetLatestVersion, PkgOption};
+use crate::{pkg_info::PrereleasePolicy, ModeGetLatestVersion, PkgOption};
 
-use super::VersionComponent;
+use super::{ModeContext, VersionComponent};
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
 #[serde(rename_all = "kebab-case")]
+#[schemars(rename = "BashCmdReleaseHandler")]
 pub struct ReleaseHandler<'a> {
+    /// The bash command printing the latest version info.
     #[serde(borrow)]
     pub command: Cow<'a, str>,
 }
@@ -28,23 +35,57 @@ impl<'a> ModeGetLatestVersion for ReleaseHandler<'a> {
     async fn get_latest_version(
         &self,
         option: &PkgOption,
-        tmp_dir: &Path,
-        in_test_mode: bool,
+        context: ModeContext<'_>,
+    ) -> anyhow::Result<VersionComponent> {
+        self.run(None, option, context).await
+    }
+
+    async fn get_version(
+        &self,
+        version: &str,
+        option: &PkgOption,
+        context: ModeContext<'_>,
+    ) -> anyhow::Result<VersionComponent> {
+        self.run(Some(version), option, context).await
+    }
+}
+
+impl<'a> ReleaseHandler<'a> {
+    async fn run(
+        &self,
+        requested_version: Option<&str>,
+        option: &PkgOption,
+        context: ModeContext<'_>,
     ) -> anyhow::Result<VersionComponent> {
         let mut cmd = Command::new("bash");
 
-        if option.allow_prerelease {
+        let prerelease_policy = option.prerelease_policy();
+        if prerelease_policy != PrereleasePolicy::StableOnly {
             cmd.env("ALLOW_PRERELEASE", "1");
         }
+        cmd.env("PRERELEASE_POLICY", prerelease_policy.as_str());
+        if let Some(version) = requested_version {
+            cmd.env("REQUESTED_VERSION", version);
+        }
+        if let Some(constraint) = &option.version_constraint {
+            cmd.env("VERSION_CONSTRAINT", constraint.to_string());
+        }
         cmd.args(["-c", &self.command])
             .envs([
                 (
                     "TEST",
-                    OsStr::new(if in_test_mode { "true" } else { "false" }),
+                    OsStr::new(if context.in_test_mode {
+                        "true"
+                    } else {
+                        "false"
+                    }),
                 ),
-                ("TMP_DIR", tmp_dir.as_os_str()),
+                ("TMP_DIR", context.tmp_dir.as_os_str()),
             ])
             .env_remove("GITHUB_TOKEN")
+            .env_remove("GH_TOKEN")
+            .env_remove("GITHUB_ENTERPRISE_TOKEN")
+            .env_remove("GH_ENTERPRISE_TOKEN")
             .stderr(Stdio::inherit());
         log::trace!("Configure the command: {cmd:#?}");
 
@@ -1,62 +1,344 @@
-use std::{borrow::Cow, collections::HashMap, fmt::Debug, ops::Deref, path::Path};
+mod credentials;
+mod scaffold;
+mod version_source;
+
+use std::{borrow::Cow, collections::HashMap, fmt::Debug, ops::Deref, str::FromStr};
 
 use anyhow::Context;
-use reqwest::header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION};
+use itertools::Itertools;
+use reqwest::header::HeaderName;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    pkg_info::{Arch, Digest, VersionContent, VersionedArchEntry},
-    version::RawVersion,
+    pkg_info::{Arch, DigestAlgorithm, PrereleasePolicy, VersionContent, VersionedArchEntry},
+    version::{self, RawVersion, Version},
     PkgOption,
 };
 
-use super::{ModeGetLatestVersion, VersionComponent};
+use super::{ModeContext, ModeGetLatestVersion, ReleaseNotes, VersionComponent};
+
+pub use credentials::GithubTokenFile;
+pub use version_source::{ReleaseField, VersionRegex, VersionSource};
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[schemars(rename = "GithubReleaseHandler")]
 pub struct ReleaseHandler<'a> {
+    /// The GitHub repository path.
+    #[schemars(regex(pattern = r"^[\w-]+/[\w-]+$"), example = "gohugoio/hugo")]
     pub repository_path: &'a str,
+    /// The regex used to select the release asset for each arch.
     pub arch_asset_patterns: ArchAssetPattern,
+    /// Where the version of a release is taken from.
+    #[serde(default, skip_serializing_if = "VersionSource::is_default")]
+    pub version_source: VersionSource,
+    /// The arches a release must provide an asset for, default to every arch of `arch_asset_patterns`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(extend("uniqueItems" = true))]
+    pub required_arches: Option<Vec<Arch>>,
+    /// What to do when the latest release lacks the asset of a required arch.
+    #[serde(default, skip_serializing_if = "MissingArchPolicy::is_default")]
+    pub missing_arch_policy: MissingArchPolicy,
+    /// The base URL of the GitHub API, default to `https://api.github.com`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(example = "https://github.example.com/api/v3")]
+    pub api_base_url: Option<url::Url>,
+    /// Download the assets through the GitHub API instead of their browser URL (required for private repositories).
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub download_through_api: bool,
+    /// The `download_url` recorded for an asset, `{tag_name}` & `{filename}` are substituted.
+    ///
+    /// Default to the browser download URL of the asset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(example = "https://mirror.example.com/hugo/{tag_name}/{filename}")]
+    pub download_url_template: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Eq, Clone, Copy, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum MissingArchPolicy {
+    /// Fail the run.
+    #[default]
+    Fail,
+    /// Use the most recent release providing every required arch.
+    Fallback,
+}
+
+impl MissingArchPolicy {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
 }
 
 impl<'a> ModeGetLatestVersion for ReleaseHandler<'a> {
     async fn get_latest_version(
         &self,
         option: &PkgOption,
-        tmp_dir: &Path,
-        in_test_mode: bool,
+        context: ModeContext<'_>,
+    ) -> anyhow::Result<VersionComponent> {
+        let github_client = GithubClient::from_env(context, self.api_base_url.as_ref())?;
+        let required_arches = self.required_arches()?;
+        let fallback = self.missing_arch_policy == MissingArchPolicy::Fallback;
+
+        log::info!("Fetching latest release ...");
+        let release = get_release(
+            &github_client,
+            self.repository_path,
+            option,
+            |release| {
+                self.version_source
+                    .version_of(release, &self.arch_asset_patterns)
+            },
+            |release| {
+                let missing_arches = self.missing_arches(release, &required_arches);
+                if fallback && !missing_arches.is_empty() {
+                    log::warn!(
+                        "Skipping release {}, no asset for {}",
+                        release.name,
+                        missing_arches.iter().join(", ")
+                    );
+                    return false;
+                }
+                true
+            },
+        )
+        .await?;
+        self.collect_release(&github_client, release, context).await
+    }
+
+    async fn get_version(
+        &self,
+        version: &str,
+        option: &PkgOption,
+        context: ModeContext<'_>,
     ) -> anyhow::Result<VersionComponent> {
-        let github_token = std::env::var("GITHUB_TOKEN")
-            .context("Cannot retrieve github token from env value `GITHUB_TOKEN`")?;
-        let http_client = build_http_client(&github_token)?;
+        let github_client = GithubClient::from_env(context, self.api_base_url.as_ref())?;
+
+        log::info!("Fetching release for version {version} ...");
+        let release = self
+            .find_release_for_version(&github_client, version, option)
+            .await?
+            .with_context(|| {
+                format!(
+                    "No release found for version {version} in {}",
+                    self.repository_path
+                )
+            })?;
+        self.collect_release(&github_client, release, context).await
+    }
+}
+
+impl<'a> ReleaseHandler<'a> {
+    /// Prepare a handler for `repository_path` by guessing the linux assets of its latest release.
+    ///
+    /// Also return the options matching the release naming (e.g. `strip_v_prefix`).
+    pub async fn scaffold(
+        repository_path: &'a str,
+        context: ModeContext<'_>,
+    ) -> anyhow::Result<(Self, PkgOption)> {
+        let github_client = GithubClient::from_env(context, None)?;
 
         log::info!("Fetching latest release ...");
-        let release =
-            get_release(&http_client, self.repository_path, option.allow_prerelease).await?;
-        if in_test_mode {
-            let path = tmp_dir.join("latest-release.json");
+        let release = get_latest_release(&github_client, repository_path).await?;
+        log::info!(
+            "Latest release {}, found {} asset(s)",
+            release.name,
+            release.assets.len()
+        );
+
+        let version = release.tag_name.trim_start_matches('v');
+        let arch_asset_patterns = scaffold::guess_arch_asset_patterns(
+            release.assets.iter().map(|asset| asset.name.as_ref()),
+            version,
+        );
+        anyhow::ensure!(
+            !arch_asset_patterns.is_empty(),
+            "No linux amd64 or arm64 asset found in release {}",
+            release.name
+        );
+        for (arch, pattern) in arch_asset_patterns.iter() {
+            let matching_assets = release
+                .assets
+                .iter()
+                .filter(|asset| pattern.is_match(&asset.name))
+                .count();
+            if matching_assets > 1 {
+                log::warn!(
+                    "The pattern for {arch} match {matching_assets} assets, review `{pattern}`"
+                );
+            }
+        }
+
+        // Prefer the tag when the title is not a plain version (e.g. `Hugo v0.119.0`).
+        let (version_source, raw_version) = if version::ParsedVersion::from_str(&release.name)
+            .is_err()
+            && version::ParsedVersion::from_str(&release.tag_name).is_ok()
+        {
+            (VersionSource::TagName, &release.tag_name)
+        } else {
+            (VersionSource::Name, &release.name)
+        };
+        let option = PkgOption {
+            strip_v_prefix: raw_version.starts_with('v'),
+            ..Default::default()
+        };
+        Ok((
+            Self {
+                repository_path,
+                arch_asset_patterns,
+                version_source,
+                required_arches: None,
+                missing_arch_policy: MissingArchPolicy::default(),
+                api_base_url: context
+                    .github_api_url
+                    .filter(|url| url.as_str().trim_end_matches('/') != DEFAULT_API_BASE_URL)
+                    .cloned(),
+                download_through_api: false,
+                download_url_template: None,
+            },
+            option,
+        ))
+    }
+
+    /// Retrieve the release page & notes of `version`.
+    pub async fn get_release_notes(
+        &self,
+        version: &str,
+        option: &PkgOption,
+        context: ModeContext<'_>,
+    ) -> anyhow::Result<Option<ReleaseNotes>> {
+        let github_client = GithubClient::from_env(context, self.api_base_url.as_ref())?;
+
+        let release = self
+            .find_release_for_version(&github_client, version, option)
+            .await?;
+        Ok(release.map(|release| ReleaseNotes {
+            url: release.html_url,
+            body: release
+                .body
+                .map(Cow::into_owned)
+                .filter(|body| !body.trim().is_empty()),
+        }))
+    }
+
+    /// Find the release of `version`.
+    ///
+    /// The release tagged with `version` (or `v{version}` when the `v` prefix is stripped) is tried first,
+    /// then the releases are scanned for the one whose transformed version is `version`.
+    async fn find_release_for_version(
+        &self,
+        github_client: &GithubClient<'_>,
+        version: &str,
+        option: &PkgOption,
+    ) -> anyhow::Result<Option<GithubRelease<'static>>> {
+        let release = get_release_by_tag(github_client, self.repository_path, version).await?;
+        if release.is_some() {
+            return Ok(release);
+        }
+        if option.strip_v_prefix && !version.starts_with('v') {
+            let tag = format!("v{version}");
+            log::debug!("No release tagged {version}, trying {tag}");
+            let release = get_release_by_tag(github_client, self.repository_path, &tag).await?;
+            if release.is_some() {
+                return Ok(release);
+            }
+        }
+
+        log::debug!("No release tagged {version}, scanning the releases");
+        scan_releases_for_version(
+            option,
+            |release| {
+                self.version_source
+                    .version_of(release, &self.arch_asset_patterns)
+            },
+            version,
+            release_page_fetcher(github_client, self.repository_path),
+        )
+        .await
+    }
+
+    async fn collect_release(
+        &self,
+        github_client: &GithubClient<'_>,
+        release: GithubRelease<'static>,
+        context: ModeContext<'_>,
+    ) -> anyhow::Result<VersionComponent> {
+        if context.in_test_mode {
+            let path = context.tmp_dir.join("latest-release.json");
             log::trace!("Dump release json to {}", path.display());
             serde_json::to_writer(std::fs::File::create(path).unwrap(), &release).unwrap();
         }
         log::info!(
-            "Latest release {}, found {} asset(s)",
+            "Release {}, found {} asset(s)",
             release.name,
             release.assets.len()
         );
 
+        let missing_arches = self.missing_arches(&release, &self.required_arches()?);
+        anyhow::ensure!(
+            missing_arches.is_empty(),
+            "Release {} has no asset for the required arch(es) {} \
+            (use `\"missing_arch_policy\": \"fallback\"` to select the most recent complete release)",
+            release.name,
+            missing_arches.iter().join(", ")
+        );
+        let raw_version = self
+            .version_source
+            .version_of(&release, &self.arch_asset_patterns)?
+            .into_owned();
         let assets = self.get_assets_for_arch(release.assets);
         log::debug!("Collected assets: {assets:#?}");
         log::info!("Calculating checksum for {} asset(s) ...", assets.len());
-        let assets_with_checksum = get_checksum_for_assets(&http_client, assets).await?;
+        let mut assets_with_checksum =
+            get_checksum_for_assets(github_client, assets, self.download_through_api, context)
+                .await?;
         log::trace!("Calculated checksums: {assets_with_checksum:#?}");
+        if let Some(template) = &self.download_url_template {
+            for entry in assets_with_checksum.values_mut() {
+                entry.download_url =
+                    render_download_url(template, &release.tag_name, &entry.filename)?;
+            }
+        }
 
         Ok((
-            RawVersion::from(release.name),
+            RawVersion::from(Cow::Owned(raw_version)),
             VersionContent(assets_with_checksum),
         ))
     }
-}
 
-impl<'a> ReleaseHandler<'a> {
+    /// The arches a release must provide, every arch of `arch_asset_patterns` unless `required_arches` is set.
+    fn required_arches(&self) -> anyhow::Result<Vec<Arch>> {
+        match &self.required_arches {
+            Some(required_arches) => {
+                if let Some(arch) = required_arches
+                    .iter()
+                    .find(|arch| !self.arch_asset_patterns.contains_key(arch))
+                {
+                    anyhow::bail!(
+                        "The required arch {arch} has no pattern in `arch_asset_patterns`"
+                    );
+                }
+                Ok(required_arches.clone())
+            }
+            None => Ok(self.arch_asset_patterns.keys().copied().sorted().collect()),
+        }
+    }
+
+    /// The arches of `required_arches` without a matching asset in `release`.
+    fn missing_arches(&self, release: &GithubRelease<'_>, required_arches: &[Arch]) -> Vec<Arch> {
+        required_arches
+            .iter()
+            .filter(|arch| {
+                let pattern = &self.arch_asset_patterns[*arch];
+                !release
+                    .assets
+                    .iter()
+                    .any(|asset| pattern.is_match(&asset.name))
+            })
+            .copied()
+            .collect()
+    }
+
     fn get_assets_for_arch<'b>(
         &self,
         assets: Vec<GithubAsset<'b>>,
@@ -64,147 +346,378 @@ impl<'a> ReleaseHandler<'a> {
         self.arch_asset_patterns
             .iter()
             .filter_map(|(arch, pattern)| {
-                assets
+                let mut matching_assets = assets
                     .iter()
-                    .find(|asset| pattern.is_match(&asset.name))
-                    .map(|asset| (*arch, asset.clone()))
+                    .filter(|asset| pattern.is_match(&asset.name));
+                let asset = matching_assets.next()?;
+                let others = matching_assets.map(|asset| &asset.name).join(", ");
+                if !others.is_empty() {
+                    log::warn!(
+                        "The pattern for {arch} match several assets, using {} (also matching {others}), review `{pattern}`",
+                        asset.name
+                    );
+                }
+                Some((*arch, asset.clone()))
             })
             .collect()
     }
 }
 
-fn build_http_client(github_token: &str) -> anyhow::Result<reqwest::Client> {
-    crate::reqwest_utils::prepare_http_client_json()
-        .default_headers(HeaderMap::from_iter([
-            (
-                AUTHORIZATION,
-                format!("Bearer {github_token}")
-                    .parse()
-                    .context("Cannot convert github token into header value")?,
-            ),
-            (
-                HeaderName::from_static("x-github-api-version"),
-                HeaderValue::from_static("2022-11-28"),
+/// The GitHub API used when no base URL is configured.
+const DEFAULT_API_BASE_URL: &str = "https://api.github.com";
+
+/// The base URL of the GitHub API: the explicit override, the configured `api_base_url`,
+/// the one provided by the env then the default one.
+fn resolve_api_base_url<'u>(
+    override_url: Option<&'u url::Url>,
+    api_base_url: Option<&'u url::Url>,
+    env_url: Option<&'u url::Url>,
+) -> &'u str {
+    override_url
+        .or(api_base_url)
+        .or(env_url)
+        .map_or(DEFAULT_API_BASE_URL, url::Url::as_str)
+        .trim_end_matches('/')
+}
+
+/// A HTTP client authenticated against the GitHub API.
+struct GithubClient<'a> {
+    http_client: &'a reqwest::Client,
+    download_client: &'a reqwest::Client,
+    api_base_url: &'a str,
+    /// The host the token belongs to, it is only sent to this host.
+    host: String,
+    token: Option<String>,
+}
+
+impl<'a> GithubClient<'a> {
+    fn from_env(
+        context: ModeContext<'a>,
+        api_base_url: Option<&'a url::Url>,
+    ) -> anyhow::Result<Self> {
+        let api_base_url = resolve_api_base_url(
+            context.github_api_url,
+            api_base_url,
+            context.env_github_api_url,
+        );
+        log::debug!("Using the GitHub API at {api_base_url}");
+        let parsed_url = url::Url::parse(api_base_url)
+            .with_context(|| format!("Invalid GitHub API URL {api_base_url}"))?;
+        let host = credentials::credential_host(&parsed_url)
+            .with_context(|| format!("No host in the GitHub API URL {api_base_url}"))?
+            .to_owned();
+
+        let token = credentials::find_token(&host, context.github_token_files)?;
+        match &token {
+            Some(token) => log::debug!("Using the token of {host} from {}", token.source),
+            None => log::warn!(
+                "No token found for {host}, using unauthenticated requests (strictly rate limited)"
             ),
-        ]))
-        .build()
-        .context("Failed to build HTTP client")
+        }
+        Ok(Self {
+            http_client: context.http_client,
+            download_client: context.download_client,
+            api_base_url,
+            host,
+            token: token.map(|token| token.secret),
+        })
+    }
+
+    /// The URL of the API endpoint at `path` (e.g. `repos/owner/repo/releases`).
+    fn api_url(&self, path: &str) -> String {
+        format!("{}/{path}", self.api_base_url)
+    }
+
+    /// Prepare a GET request, the token is only attached when `url` is on the host it belongs to.
+    fn get(&self, url: &str) -> reqwest::RequestBuilder {
+        self.authenticated(self.http_client, url)
+    }
+
+    /// Prepare a GET request downloading an asset as-is, see [`GithubClient::get`].
+    fn get_asset(&self, url: &str) -> reqwest::RequestBuilder {
+        self.authenticated(self.download_client, url)
+    }
+
+    fn authenticated(&self, client: &reqwest::Client, url: &str) -> reqwest::RequestBuilder {
+        let request = client.get(url).header(
+            HeaderName::from_static("x-github-api-version"),
+            "2022-11-28",
+        );
+        match &self.token {
+            Some(token)
+                if url::Url::parse(url).is_ok_and(|url| {
+                    credentials::credential_host(&url) == Some(self.host.as_str())
+                }) =>
+            {
+                request.bearer_auth(token)
+            }
+            _ => request,
+        }
+    }
+}
+
+/// Render the `download_url` of an asset from `template`.
+fn render_download_url(template: &str, tag_name: &str, filename: &str) -> anyhow::Result<url::Url> {
+    let raw_url = template
+        .replace("{tag_name}", tag_name)
+        .replace("{filename}", filename);
+    raw_url.parse().with_context(|| {
+        format!("Invalid URL `{raw_url}` rendered from the `download_url_template`")
+    })
 }
 
+/// Download the assets to compute their digest,
+/// through their API URL when `through_api` is set (required for private repositories).
 async fn get_checksum_for_assets<'a>(
-    http_client: &reqwest::Client,
+    github_client: &GithubClient<'_>,
     assets: HashMap<Arch, GithubAsset<'a>>,
+    through_api: bool,
+    context: ModeContext<'_>,
 ) -> anyhow::Result<HashMap<Arch, VersionedArchEntry<'a>>> {
-    use futures::{FutureExt, TryStreamExt};
-    use sha2::Digest as Sha2Digest;
-
-    let responses_to_collect = assets.into_iter().map(|(arch, asset)| {
-        http_client
-            .get(asset.browser_download_url.clone())
-            .send()
-            .map(move |res_resp| {
-                res_resp.map_err(anyhow::Error::from).and_then(|response| {
-                    anyhow::ensure!(
-                        response.status() == reqwest::StatusCode::OK,
-                        "Invalid response status code for asset {}",
+    use futures::FutureExt;
+
+    let requests = assets
+        .into_iter()
+        .map(|(arch, asset)| {
+            let request = if through_api {
+                let url = asset.url.clone().with_context(|| {
+                    format!(
+                        "No API URL for asset {} to download it through the API",
                         asset.name
-                    );
+                    )
+                })?;
+                github_client
+                    .get_asset(url.as_str())
+                    .header(reqwest::header::ACCEPT, "application/octet-stream")
+            } else {
+                github_client.get_asset(asset.browser_download_url.as_str())
+            };
+            Ok((arch, asset, request))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let responses_to_collect = requests.into_iter().map(|(arch, asset, request)| {
+        request.send().map(move |res_resp| {
+            res_resp.map_err(anyhow::Error::from).and_then(|response| {
+                anyhow::ensure!(
+                    response.status() == reqwest::StatusCode::OK,
+                    "Invalid response status code for asset {}",
+                    asset.name
+                );
 
-                    Ok((arch, asset, response))
-                })
+                Ok((arch, asset, response))
             })
+        })
     });
     let responses = futures::future::try_join_all(responses_to_collect).await?;
     let checksum_to_collect = responses.into_iter().map(|(arch, asset, response)| {
-        response
-            .bytes_stream()
-            .try_fold(
-                (0_usize, sha2::Sha512::new()),
-                |(size, mut hash), chunk| async move {
-                    hash.update(&chunk);
-                    Ok((size + chunk.len(), hash))
-                },
-            )
-            .map(move |res| {
-                res.map_err(anyhow::Error::from)
-                    .and_then(|(dl_size, hash)| {
-                        anyhow::ensure!(
-                            dl_size == asset.size,
-                            "Invalid download size for asset {}",
-                            asset.name
-                        );
-                        let res = hash.finalize();
-                        Ok((
-                            arch,
-                            VersionedArchEntry {
-                                filename: asset.name,
-                                download_url: asset.browser_download_url,
-                                digest: Digest::Sha512(Cow::Owned(bytes_to_hex_str(&res))),
-                            },
-                        ))
-                    })
-            })
+        crate::reqwest_utils::digest_response(response, DigestAlgorithm::Sha512).map(move |res| {
+            res.map_err(anyhow::Error::from)
+                .and_then(|(dl_size, digest)| {
+                    context.record_download(dl_size as u64);
+                    anyhow::ensure!(
+                        dl_size == asset.size,
+                        "Invalid download size for asset {}",
+                        asset.name
+                    );
+                    Ok((
+                        arch,
+                        VersionedArchEntry {
+                            filename: asset.name,
+                            download_url: asset.browser_download_url,
+                            digest,
+                        },
+                    ))
+                })
+        })
     });
     let assets_with_checksums = futures::future::try_join_all(checksum_to_collect).await?;
     Ok(assets_with_checksums.into_iter().collect())
 }
 
-fn bytes_to_hex_str(bytes: &[u8]) -> String {
-    use std::fmt::Write;
-
-    let mut res = String::with_capacity(bytes.len() * 2);
+/// The number of releases requested per page when scanning the releases.
+const RELEASE_PER_PAGE: usize = 30;
+/// The number of pages scanned before giving up on finding a matching release.
+const MAX_RELEASE_PAGES: usize = 10;
 
-    bytes
-        .iter()
-        .for_each(|byte| write!(&mut res, "{byte:02x}").unwrap());
+/// Retrieve the most recent release allowed by `option`, `version_of` extract the version of a release
+/// and `is_complete` tells whether it provides the required assets.
+async fn get_release(
+    github_client: &GithubClient<'_>,
+    repository_path: &str,
+    option: &PkgOption,
+    version_of: impl for<'r> Fn(&'r GithubRelease<'_>) -> anyhow::Result<Cow<'r, str>>,
+    is_complete: impl Fn(&GithubRelease<'_>) -> bool,
+) -> anyhow::Result<GithubRelease<'static>> {
+    let prerelease_policy = option.prerelease_policy();
+    if prerelease_policy == PrereleasePolicy::StableOnly && option.version_constraint.is_none() {
+        let release = get_latest_release(github_client, repository_path).await?;
+        if is_complete(&release) {
+            return Ok(release);
+        }
+        log::info!("Looking for the most recent complete release ...");
+    }
+    let release = scan_releases(
+        option,
+        version_of,
+        is_complete,
+        release_page_fetcher(github_client, repository_path),
+    )
+    .await?;
 
-    res
+    release.ok_or_else(|| {
+        let constraint = option
+            .version_constraint
+            .as_ref()
+            .map(|constraint| format!(" and the version constraint `{constraint}`"))
+            .unwrap_or_default();
+        anyhow::anyhow!(
+            "No release of {repository_path} matching the prerelease policy `{prerelease_policy}`{constraint} \
+            in the {} most recent releases",
+            RELEASE_PER_PAGE * MAX_RELEASE_PAGES
+        )
+    })
 }
 
-async fn get_release(
-    http_client: &reqwest::Client,
+/// Fetch a page (starting from 1) of the releases of `repository_path`, most recent first.
+fn release_page_fetcher<'c>(
+    github_client: &'c GithubClient<'_>,
     repository_path: &str,
-    allow_prerelease: bool,
-) -> anyhow::Result<GithubRelease<'static>> {
-    const RELEASE_PER_PAGE: usize = 10;
+) -> impl FnMut(usize) -> futures::future::BoxFuture<'c, anyhow::Result<String>> + 'c {
+    let url = github_client.api_url(&format!("repos/{repository_path}/releases"));
+    move |page| {
+        let request = github_client
+            .get(&url)
+            .query(&[("per_page", RELEASE_PER_PAGE), ("page", page)]);
+        Box::pin(async move {
+            let res = request.send().await?;
 
-    if !allow_prerelease {
-        return get_latest_release(http_client, repository_path).await;
+            anyhow::ensure!(
+                res.status() == reqwest::StatusCode::OK,
+                "Invalid response status: {}",
+                res.status(),
+            );
+
+            Ok(res.text().await?)
+        })
     }
-    let url = format!("https://api.github.com/repos/{repository_path}/releases");
-    let mut page = 1;
-    loop {
-        let res = http_client
-            .get(&url)
-            .query(&[("per_page", RELEASE_PER_PAGE), ("page", page)])
-            .send()
-            .await?;
+}
 
-        anyhow::ensure!(
-            res.status() == reqwest::StatusCode::OK,
-            "Invalid response status: {}",
-            res.status(),
-        );
+/// Scan the pages of releases returned by `fetch_page` for the one whose version,
+/// once transformed according to `option`, is `version`.
+///
+/// At most [`MAX_RELEASE_PAGES`] pages are fetched, `None` when no release matches.
+async fn scan_releases_for_version<F, Fut>(
+    option: &PkgOption,
+    version_of: impl for<'r> Fn(&'r GithubRelease<'_>) -> anyhow::Result<Cow<'r, str>>,
+    version: &str,
+    mut fetch_page: F,
+) -> anyhow::Result<Option<GithubRelease<'static>>>
+where
+    F: FnMut(usize) -> Fut,
+    Fut: std::future::Future<Output = anyhow::Result<String>>,
+{
+    for page in 1..=MAX_RELEASE_PAGES {
+        let raw_body = fetch_page(page).await?;
+        let releases = serde_json::from_str::<Vec<GithubRelease>>(&raw_body)?;
+        for release in &releases {
+            if release.draft {
+                continue;
+            }
+            let release_version =
+                match version_of(release).and_then(|raw| Ok(Version::from_raw_str(raw, option)?)) {
+                    Ok(release_version) => release_version,
+                    Err(e) => {
+                        log::debug!("Skipping release {}: {e}", release.name);
+                        continue;
+                    }
+                };
+            if release_version.as_str() == version {
+                return Ok(Some(release.to_owned()));
+            }
+        }
+        if releases.len() < RELEASE_PER_PAGE {
+            return Ok(None);
+        }
+    }
+    log::warn!(
+        "Stopped scanning the releases after {} pages",
+        MAX_RELEASE_PAGES
+    );
+    Ok(None)
+}
 
-        let raw_body = res.text().await?;
+/// Scan the pages of releases (most recent first) returned by `fetch_page`
+/// for the complete release selected by the prerelease policy & the version constraint of `option`.
+///
+/// At most [`MAX_RELEASE_PAGES`] pages are fetched, `None` when no release matches.
+async fn scan_releases<F, Fut>(
+    option: &PkgOption,
+    version_of: impl for<'r> Fn(&'r GithubRelease<'_>) -> anyhow::Result<Cow<'r, str>>,
+    is_complete: impl Fn(&GithubRelease<'_>) -> bool,
+    mut fetch_page: F,
+) -> anyhow::Result<Option<GithubRelease<'static>>>
+where
+    F: FnMut(usize) -> Fut,
+    Fut: std::future::Future<Output = anyhow::Result<String>>,
+{
+    let prerelease_policy = option.prerelease_policy();
+    let mut prerelease_fallback = None;
+    for page in 1..=MAX_RELEASE_PAGES {
+        let raw_body = fetch_page(page).await?;
         let releases = serde_json::from_str::<Vec<GithubRelease>>(&raw_body)?;
-        for release in releases {
-            if !release.draft && release.prerelease == allow_prerelease {
-                return Ok(release.to_owned());
+        for release in &releases {
+            if release.draft
+                || (release.prerelease && prerelease_policy == PrereleasePolicy::StableOnly)
+            {
+                continue;
             }
+            if let Some(constraint) = &option.version_constraint {
+                let version = match version_of(release)
+                    .and_then(|raw| Ok(Version::from_raw_str(raw, option)?))
+                {
+                    Ok(version) => version,
+                    Err(e) => {
+                        log::debug!("Skipping release {}: {e}", release.name);
+                        continue;
+                    }
+                };
+                if !constraint.matches(version.as_str()) {
+                    log::debug!(
+                        "Skipping release {}, not in the version constraint",
+                        release.name
+                    );
+                    continue;
+                }
+            }
+            if !is_complete(release) {
+                continue;
+            }
+            if release.prerelease && prerelease_policy == PrereleasePolicy::PreferStable {
+                if prerelease_fallback.is_none() {
+                    log::debug!("Keeping prerelease {} as a fallback", release.name);
+                    prerelease_fallback = Some(release.to_owned());
+                }
+                continue;
+            }
+            return Ok(Some(release.to_owned()));
+        }
+        if releases.len() < RELEASE_PER_PAGE {
+            return Ok(prerelease_fallback);
         }
-        page += 1;
     }
+    log::warn!(
+        "Stopped scanning the releases after {} pages",
+        MAX_RELEASE_PAGES
+    );
+    Ok(prerelease_fallback)
 }
 
 async fn get_latest_release(
-    http_client: &reqwest::Client,
+    github_client: &GithubClient<'_>,
     repository_path: &str,
 ) -> anyhow::Result<GithubRelease<'static>> {
-    let res = http_client
-        .get(format!(
-            "https://api.github.com/repos/{repository_path}/releases/latest",
-        ))
+    let res = github_client
+        .get(&github_client.api_url(&format!("repos/{repository_path}/releases/latest")))
         .send()
         .await?;
 
@@ -218,6 +731,42 @@ async fn get_latest_release(
     Ok(serde_json::from_str::<GithubRelease>(&raw_body)?.to_owned())
 }
 
+/// Retrieve the release associated to `tag`, `None` if no such release exist.
+async fn get_release_by_tag(
+    github_client: &GithubClient<'_>,
+    repository_path: &str,
+    tag: &str,
+) -> anyhow::Result<Option<GithubRelease<'static>>> {
+    let tags_url = github_client.api_url(&format!("repos/{repository_path}/releases/tags"));
+    let res = github_client
+        .get(release_tag_url(&tags_url, tag)?.as_str())
+        .send()
+        .await?;
+
+    if res.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    anyhow::ensure!(
+        res.status() == reqwest::StatusCode::OK,
+        "Invalid response status: {}",
+        res.status(),
+    );
+
+    let raw_body = res.text().await?;
+    Ok(Some(
+        serde_json::from_str::<GithubRelease>(&raw_body)?.to_owned(),
+    ))
+}
+
+/// The URL of the release of `tag` under `tags_url`, the tag is percent-encoded (it may contain a `/`).
+fn release_tag_url(tags_url: &str, tag: &str) -> anyhow::Result<url::Url> {
+    let mut url = url::Url::parse(tags_url)?;
+    url.path_segments_mut()
+        .map_err(|()| anyhow::anyhow!("Invalid API URL {tags_url}"))?
+        .push(tag);
+    Ok(url)
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct ArchAssetPattern(#[serde(with = "arch_pattern_map")] HashMap<Arch, regex::Regex>);
 
@@ -242,6 +791,23 @@ impl Deref for ArchAssetPattern {
     }
 }
 
+impl JsonSchema for ArchAssetPattern {
+    fn schema_name() -> Cow<'static, str> {
+        Cow::Borrowed("ArchAssetPattern")
+    }
+
+    fn json_schema(generator: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        schemars::json_schema!({
+            "type": "object",
+            "propertyNames": generator.subschema_for::<Arch>(),
+            "additionalProperties": {
+                "type": "string",
+                "format": "regex"
+            }
+        })
+    }
+}
+
 impl Debug for ArchAssetPattern {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_map()
@@ -314,6 +880,9 @@ pub struct GithubRelease<'a> {
     name: Cow<'a, str>,
     #[serde(borrow)]
     tag_name: Cow<'a, str>,
+    html_url: url::Url,
+    #[serde(borrow, default)]
+    body: Option<Cow<'a, str>>,
     prerelease: bool,
     draft: bool,
     #[serde(borrow)]
@@ -325,6 +894,11 @@ impl GithubRelease<'_> {
         GithubRelease {
             name: Cow::Owned(self.name.clone().into()),
             tag_name: Cow::Owned(self.tag_name.clone().into()),
+            html_url: self.html_url.clone(),
+            body: self
+                .body
+                .as_ref()
+                .map(|body| Cow::Owned(body.clone().into())),
             prerelease: self.prerelease,
             draft: self.draft,
             assets: self.assets.iter().map(GithubAsset::to_owned).collect(),
@@ -339,6 +913,9 @@ pub struct GithubAsset<'a> {
     name: Cow<'a, str>,
     size: usize,
     browser_download_url: url::Url,
+    /// The API URL of the asset, downloading it requires `Accept: application/octet-stream`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    url: Option<url::Url>,
 }
 
 impl GithubAsset<'_> {
@@ -347,6 +924,7 @@ impl GithubAsset<'_> {
             name: Cow::Owned(self.name.clone().into()),
             size: self.size,
             browser_download_url: self.browser_download_url.clone(),
+            url: self.url.clone(),
         }
     }
 }
@@ -357,6 +935,7 @@ impl<'a> Debug for GithubAsset<'a> {
             .field("name", &self.name)
             .field("size", &self.size)
             .field("browser_download_url", &self.browser_download_url.as_str())
+            .field("url", &self.url.as_ref().map(url::Url::as_str))
             .finish()
     }
 }
@@ -365,12 +944,15 @@ impl<'a> Debug for GithubAsset<'a> {
 mod tests {
     use super::*;
 
+    use rstest::rstest;
+
     #[test]
     fn can_get_asset_for_arch() {
         let wanted_asset = GithubAsset {
             name: "asset.foo".into(),
             size: 0,
             browser_download_url: "http://asset.com".parse().unwrap(),
+            url: None,
         };
         let assets = vec![
             wanted_asset.clone(),
@@ -378,6 +960,7 @@ mod tests {
                 name: "asset.bar".into(),
                 size: 0,
                 browser_download_url: "http://asset.com".parse().unwrap(),
+                url: None,
             },
         ];
         let handler = ReleaseHandler {
@@ -386,6 +969,12 @@ mod tests {
                 Arch::Amd64,
                 regex::Regex::new(r"asset\.foo").unwrap(),
             )]),
+            version_source: VersionSource::default(),
+            required_arches: None,
+            missing_arch_policy: MissingArchPolicy::default(),
+            api_base_url: None,
+            download_through_api: false,
+            download_url_template: None,
         };
 
         let got_asset = handler.get_assets_for_arch(assets);
@@ -401,6 +990,7 @@ mod tests {
             name: "asset.foo".into(),
             size: 0,
             browser_download_url: "http://asset.com".parse().unwrap(),
+            url: None,
         };
         let assets = vec![foo_asset.clone()];
         let handler = ReleaseHandler {
@@ -409,6 +999,12 @@ mod tests {
                 (Arch::Amd64, regex::Regex::new(r"asset\.foo").unwrap()),
                 (Arch::Arm64, regex::Regex::new(r"asset\.foo").unwrap()),
             ]),
+            version_source: VersionSource::default(),
+            required_arches: None,
+            missing_arch_policy: MissingArchPolicy::default(),
+            api_base_url: None,
+            download_through_api: false,
+            download_url_template: None,
         };
 
         let got_asset = handler.get_assets_for_arch(assets);
@@ -419,4 +1015,366 @@ mod tests {
             HashMap::from_iter([(Arch::Amd64, foo_asset.clone()), (Arch::Arm64, foo_asset),])
         );
     }
+
+    fn handler(required_arches: Option<Vec<Arch>>) -> ReleaseHandler<'static> {
+        ReleaseHandler {
+            repository_path: "",
+            arch_asset_patterns: ArchAssetPattern::from_iter(vec![
+                (Arch::Amd64, regex::Regex::new(r"amd64\.tar\.gz$").unwrap()),
+                (Arch::Arm64, regex::Regex::new(r"arm64\.tar\.gz$").unwrap()),
+            ]),
+            version_source: VersionSource::default(),
+            required_arches,
+            missing_arch_policy: MissingArchPolicy::default(),
+            api_base_url: None,
+            download_through_api: false,
+            download_url_template: None,
+        }
+    }
+
+    #[rstest]
+    #[case::default_every_arch(None, &["foo-amd64.tar.gz"], &[Arch::Arm64])]
+    #[case::complete(None, &["foo-amd64.tar.gz", "foo-arm64.tar.gz"], &[])]
+    #[case::optional_arch(Some(vec![Arch::Amd64]), &["foo-amd64.tar.gz"], &[])]
+    #[case::required_arch(Some(vec![Arch::Amd64]), &["foo-arm64.tar.gz"], &[Arch::Amd64])]
+    fn test_missing_arches(
+        #[case] required_arches: Option<Vec<Arch>>,
+        #[case] asset_names: &[&str],
+        #[case] expected: &[Arch],
+    ) {
+        let handler = handler(required_arches);
+        let release = GithubRelease {
+            name: "v1.0.0".into(),
+            tag_name: "v1.0.0".into(),
+            html_url: "https://github.com/foo/bar/releases/tag/v1.0.0"
+                .parse()
+                .unwrap(),
+            body: None,
+            prerelease: false,
+            draft: false,
+            assets: asset_names
+                .iter()
+                .map(|name| GithubAsset {
+                    name: Cow::Borrowed(name),
+                    size: 0,
+                    browser_download_url: "http://asset.com".parse().unwrap(),
+                    url: None,
+                })
+                .collect(),
+        };
+
+        let missing_arches = handler.missing_arches(&release, &handler.required_arches().unwrap());
+
+        assert_eq!(missing_arches, expected);
+    }
+
+    #[test]
+    fn required_arch_without_pattern() {
+        let mut handler = handler(Some(vec![Arch::Arm64]));
+        handler.arch_asset_patterns.0.remove(&Arch::Arm64);
+
+        assert_eq!(
+            handler.required_arches().unwrap_err().to_string(),
+            "The required arch arm64 has no pattern in `arch_asset_patterns`"
+        );
+    }
+
+    #[test]
+    fn can_render_download_url() {
+        let url = render_download_url(
+            "https://mirror.example.com/hugo/{tag_name}/{filename}",
+            "v0.119.0",
+            "hugo_0.119.0_linux-amd64.tar.gz",
+        )
+        .unwrap();
+
+        assert_eq!(
+            url.as_str(),
+            "https://mirror.example.com/hugo/v0.119.0/hugo_0.119.0_linux-amd64.tar.gz"
+        );
+    }
+
+    #[test]
+    fn render_invalid_download_url() {
+        let err = render_download_url("{filename}", "v0.119.0", "hugo.tar.gz").unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "Invalid URL `hugo.tar.gz` rendered from the `download_url_template`"
+        );
+    }
+
+    #[rstest]
+    #[case::default(None, None, None, "https://api.github.com")]
+    #[case::configured(
+        None,
+        Some("https://github.example.com/api/v3/"),
+        None,
+        "https://github.example.com/api/v3"
+    )]
+    #[case::env(
+        None,
+        None,
+        Some("https://github.example.com/api/v3"),
+        "https://github.example.com/api/v3"
+    )]
+    // `GITHUB_API_URL` is set on every GitHub Actions runner, it must not override the pkg info.
+    #[case::configured_over_env(
+        None,
+        Some("https://github.example.com/api/v3"),
+        Some("https://api.github.com"),
+        "https://github.example.com/api/v3"
+    )]
+    #[case::overridden(
+        Some("http://127.0.0.1:8080"),
+        Some("https://github.example.com/api/v3"),
+        Some("https://api.github.com"),
+        "http://127.0.0.1:8080"
+    )]
+    fn test_resolve_api_base_url(
+        #[case] override_url: Option<&str>,
+        #[case] api_base_url: Option<&str>,
+        #[case] env_url: Option<&str>,
+        #[case] expected: &str,
+    ) {
+        let parse = |url: Option<&str>| url.map(|url| url.parse::<url::Url>().unwrap());
+        let (override_url, api_base_url, env_url) =
+            (parse(override_url), parse(api_base_url), parse(env_url));
+
+        assert_eq!(
+            resolve_api_base_url(
+                override_url.as_ref(),
+                api_base_url.as_ref(),
+                env_url.as_ref()
+            ),
+            expected
+        );
+    }
+
+    #[rstest]
+    #[case::plain("v1.2.3", "v1.2.3")]
+    #[case::slash("cli/v1.2.3", "cli%2Fv1.2.3")]
+    #[case::plus("1.2.3+build.1", "1.2.3+build.1")]
+    #[case::hash("release#1", "release%231")]
+    fn test_release_tag_url(#[case] tag: &str, #[case] expected_segment: &str) {
+        let url =
+            release_tag_url("https://api.github.com/repos/foo/bar/releases/tags", tag).unwrap();
+
+        assert_eq!(
+            url.as_str(),
+            format!("https://api.github.com/repos/foo/bar/releases/tags/{expected_segment}")
+        );
+    }
+
+    /// A page of the releases API, `(tag, prerelease)` from the most recent,
+    /// the releases tagged `*-incomplete` have no asset.
+    fn release_page(releases: &[(&str, bool)]) -> String {
+        releases
+            .iter()
+            .map(|(tag, prerelease)| {
+                serde_json::json!({
+                    "name": tag,
+                    "tag_name": tag,
+                    "html_url": format!("https://github.com/foo/bar/releases/tag/{tag}"),
+                    "prerelease": prerelease,
+                    "draft": false,
+                    "assets": if tag.ends_with("-incomplete") {
+                        serde_json::json!([])
+                    } else {
+                        serde_json::json!([{
+                            "name": format!("bar-{tag}-amd64.tar.gz"),
+                            "size": 1,
+                            "browser_download_url": "https://example.com/amd64",
+                        }])
+                    },
+                })
+            })
+            .collect::<serde_json::Value>()
+            .to_string()
+    }
+
+    /// A full page of prereleases.
+    fn prerelease_page() -> String {
+        let tags = (0..RELEASE_PER_PAGE)
+            .map(|i| format!("v3.0.0-rc.{i}"))
+            .collect::<Vec<_>>();
+        release_page(
+            &tags
+                .iter()
+                .map(|tag| (tag.as_str(), true))
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    /// Scan the canned `pages`, return the selected tag and the number of fetched pages.
+    fn scan(raw_option: &str, pages: &[String]) -> (Option<String>, usize) {
+        let option = serde_json::from_str::<PkgOption>(raw_option).unwrap();
+        let mut fetched = 0;
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+
+        let release = runtime
+            .block_on(scan_releases(
+                &option,
+                |release| Ok(Cow::Borrowed(&release.tag_name)),
+                |release| !release.assets.is_empty(),
+                |page| {
+                    fetched = page;
+                    let body = pages.get(page - 1).cloned().unwrap_or_else(|| "[]".into());
+                    async move { Ok(body) }
+                },
+            ))
+            .unwrap();
+
+        (
+            release.map(|release| release.tag_name.into_owned()),
+            fetched,
+        )
+    }
+
+    #[rstest]
+    #[case::stable_only(r#"{}"#, Some("v1.9.0"))]
+    #[case::include(r#"{ "prerelease_policy": "include" }"#, Some("v2.0.0-rc.1"))]
+    #[case::allow_prerelease(r#"{ "allow_prerelease": true }"#, Some("v2.0.0-rc.1"))]
+    #[case::prefer_stable(r#"{ "prerelease_policy": "prefer-stable" }"#, Some("v1.9.0"))]
+    #[case::constraint(
+        r#"{ "prerelease_policy": "include", "version_constraint": "<1.9" }"#,
+        Some("v1.8.1")
+    )]
+    #[case::no_match(r#"{ "version_constraint": ">=3" }"#, None)]
+    fn test_scan_releases(#[case] raw_option: &str, #[case] expected: Option<&str>) {
+        let pages = [release_page(&[
+            ("v2.0.0-rc.1", true),
+            ("v1.9.0", false),
+            ("v1.8.1", false),
+        ])];
+
+        assert_eq!(scan(raw_option, &pages), (expected.map(Into::into), 1));
+    }
+
+    #[rstest]
+    #[case::stable_only(r#"{}"#, None)]
+    #[case::include(r#"{ "prerelease_policy": "include" }"#, Some("v2.0.0-rc.2"))]
+    #[case::prefer_stable(r#"{ "prerelease_policy": "prefer-stable" }"#, Some("v2.0.0-rc.2"))]
+    fn test_scan_releases_without_stable(#[case] raw_option: &str, #[case] expected: Option<&str>) {
+        let pages = [release_page(&[
+            ("v2.0.0-rc.2", true),
+            ("v2.0.0-rc.1", true),
+        ])];
+
+        assert_eq!(scan(raw_option, &pages), (expected.map(Into::into), 1));
+    }
+
+    #[rstest]
+    // Regression: `allow_prerelease` used to only select prereleases, scanning forever without any.
+    #[case::allow_prerelease(r#"{ "allow_prerelease": true }"#, Some("v1.9.0"))]
+    #[case::include(r#"{ "prerelease_policy": "include" }"#, Some("v1.9.0"))]
+    fn test_scan_releases_without_prerelease(
+        #[case] raw_option: &str,
+        #[case] expected: Option<&str>,
+    ) {
+        let pages = [release_page(&[("v1.9.0", false), ("v1.8.1", false)])];
+
+        assert_eq!(scan(raw_option, &pages), (expected.map(Into::into), 1));
+    }
+
+    #[rstest]
+    #[case::stable_only(r#"{}"#, Some("v1.8.1"))]
+    #[case::include(r#"{ "prerelease_policy": "include" }"#, Some("v1.9.0-rc.1"))]
+    fn test_scan_releases_skip_incomplete(
+        #[case] raw_option: &str,
+        #[case] expected: Option<&str>,
+    ) {
+        let pages = [release_page(&[
+            ("v1.9.0-incomplete", false),
+            ("v1.9.0-rc.1", true),
+            ("v1.8.1", false),
+        ])];
+
+        assert_eq!(scan(raw_option, &pages), (expected.map(Into::into), 1));
+    }
+
+    #[test]
+    fn scan_releases_prefer_stable_on_next_page() {
+        let pages = [prerelease_page(), release_page(&[("v2.9.0", false)])];
+
+        assert_eq!(
+            scan(r#"{ "prerelease_policy": "prefer-stable" }"#, &pages),
+            (Some("v2.9.0".into()), 2)
+        );
+    }
+
+    #[test]
+    fn scan_releases_skip_unmatched_version_source() {
+        let option =
+            serde_json::from_str::<PkgOption>(r#"{ "version_constraint": "<2" }"#).unwrap();
+        let version_source =
+            serde_json::from_str::<VersionSource>(r#"{ "regex": { "pattern": "^v(.+)$" } }"#)
+                .unwrap();
+        let arch_asset_patterns = std::iter::empty().collect::<ArchAssetPattern>();
+        let page = release_page(&[("nightly", false), ("v2.0.0", false), ("v1.9.0", false)]);
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+
+        let release = runtime
+            .block_on(scan_releases(
+                &option,
+                |release| version_source.version_of(release, &arch_asset_patterns),
+                |release| !release.assets.is_empty(),
+                |_| {
+                    let body = page.clone();
+                    async move { Ok(body) }
+                },
+            ))
+            .unwrap();
+
+        assert_eq!(
+            release.map(|release| release.tag_name.into_owned()),
+            Some("v1.9.0".into())
+        );
+    }
+
+    #[rstest]
+    #[case::transformed("1.2.2", Some("cli/v1.2.2"))]
+    #[case::unknown("1.0.0", None)]
+    fn test_scan_releases_for_version(#[case] version: &str, #[case] expected: Option<&str>) {
+        let option = serde_json::from_str::<PkgOption>(
+            r#"{ "version_transform": [{ "strip_prefix": "cli/v" }, { "validate": "^[0-9.]+$" }] }"#,
+        )
+        .unwrap();
+        let page = release_page(&[
+            ("nightly", true),
+            ("cli/v1.2.3", false),
+            ("cli/v1.2.2", false),
+        ]);
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+
+        let release = runtime
+            .block_on(scan_releases_for_version(
+                &option,
+                |release| Ok(Cow::Borrowed(&release.tag_name)),
+                version,
+                |_| {
+                    let body = page.clone();
+                    async move { Ok(body) }
+                },
+            ))
+            .unwrap();
+
+        assert_eq!(
+            release.map(|release| release.tag_name.into_owned()),
+            expected.map(Into::into)
+        );
+    }
+
+    #[test]
+    fn scan_releases_is_bounded() {
+        let pages = vec![prerelease_page(); MAX_RELEASE_PAGES + 1];
+
+        assert_eq!(scan(r#"{}"#, &pages), (None, MAX_RELEASE_PAGES));
+    }
 }
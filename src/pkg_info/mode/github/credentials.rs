@@ -0,0 +1,249 @@
+//! The tokens used to authenticate against a GitHub instance.
+//!
+//! A token is looked up for the host of the instance (`github.com` for `api.github.com`) from, in order:
+//!
+//! 1. The token files provided with `--github-token-file [HOST=]PATH`.
+//! 2. The env variables `GH_TOKEN` & `GITHUB_TOKEN` for `github.com`,
+//!    `GH_ENTERPRISE_TOKEN` & `GITHUB_ENTERPRISE_TOKEN` for the other hosts.
+//! 3. The `hosts.yml` file of the gh CLI.
+
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use anyhow::Context;
+
+/// The host of the public GitHub instance.
+const GITHUB_HOST: &str = "github.com";
+
+/// The env variables holding a token for `github.com`.
+const GITHUB_TOKEN_ENV: [&str; 2] = ["GH_TOKEN", "GITHUB_TOKEN"];
+/// The env variables holding a token for the other hosts.
+const ENTERPRISE_TOKEN_ENV: [&str; 2] = ["GH_ENTERPRISE_TOKEN", "GITHUB_ENTERPRISE_TOKEN"];
+
+/// A file containing the token of a GitHub host.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GithubTokenFile {
+    pub host: String,
+    pub path: PathBuf,
+}
+
+impl FromStr for GithubTokenFile {
+    type Err = std::convert::Infallible;
+
+    /// Parse `[HOST=]PATH`, the host default to `github.com`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.split_once('=') {
+            Some((host, path)) if !host.is_empty() && !host.contains('/') => Self {
+                host: host.to_owned(),
+                path: PathBuf::from(path),
+            },
+            _ => Self {
+                host: GITHUB_HOST.to_owned(),
+                path: PathBuf::from(s),
+            },
+        })
+    }
+}
+
+/// Where a token was found.
+#[derive(Debug, PartialEq, Eq)]
+pub(super) enum TokenSource {
+    File(PathBuf),
+    Env(&'static str),
+    GhHosts(PathBuf),
+}
+
+impl Display for TokenSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TokenSource::File(path) => write!(f, "the token file {}", path.display()),
+            TokenSource::Env(name) => write!(f, "the env variable `{name}`"),
+            TokenSource::GhHosts(path) => write!(f, "the gh CLI config {}", path.display()),
+        }
+    }
+}
+
+pub(super) struct Token {
+    pub secret: String,
+    pub source: TokenSource,
+}
+
+/// The host the credentials of `url` are stored under, `api.github.com` is stored under `github.com`.
+pub(super) fn credential_host(url: &url::Url) -> Option<&str> {
+    match url.host_str()? {
+        "api.github.com" => Some(GITHUB_HOST),
+        host => Some(host),
+    }
+}
+
+/// Look up the token of `host`, `None` when no source provides one.
+pub(super) fn find_token(
+    host: &str,
+    token_files: &[GithubTokenFile],
+) -> anyhow::Result<Option<Token>> {
+    if let Some(token_file) = token_files
+        .iter()
+        .find(|token_file| token_file.host == host)
+    {
+        let raw = std::fs::read_to_string(&token_file.path)
+            .with_context(|| format!("Cannot read the token file {}", token_file.path.display()))?;
+        let secret = raw.trim();
+        anyhow::ensure!(
+            !secret.is_empty(),
+            "The token file {} is empty",
+            token_file.path.display()
+        );
+        return Ok(Some(Token {
+            secret: secret.to_owned(),
+            source: TokenSource::File(token_file.path.clone()),
+        }));
+    }
+
+    let env_names = if host == GITHUB_HOST {
+        GITHUB_TOKEN_ENV
+    } else {
+        ENTERPRISE_TOKEN_ENV
+    };
+    for name in env_names {
+        if let Some(secret) = std::env::var(name).ok().filter(|secret| !secret.is_empty()) {
+            return Ok(Some(Token {
+                secret,
+                source: TokenSource::Env(name),
+            }));
+        }
+    }
+
+    let Some(path) = gh_hosts_path() else {
+        return Ok(None);
+    };
+    let raw = match std::fs::read_to_string(&path) {
+        Ok(raw) => raw,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => {
+            return Err(e).with_context(|| format!("Cannot read {}", path.display()));
+        }
+    };
+    Ok(gh_hosts_token(&raw, host).map(|secret| Token {
+        secret: secret.to_owned(),
+        source: TokenSource::GhHosts(path),
+    }))
+}
+
+/// The `hosts.yml` of the gh CLI, in `$GH_CONFIG_DIR`, `$XDG_CONFIG_HOME/gh` or `~/.config/gh`.
+fn gh_hosts_path() -> Option<PathBuf> {
+    let non_empty_var = |name| std::env::var_os(name).filter(|value| !value.is_empty());
+
+    let config_dir = non_empty_var("GH_CONFIG_DIR")
+        .map(PathBuf::from)
+        .or_else(|| non_empty_var("XDG_CONFIG_HOME").map(|dir| Path::new(&dir).join("gh")))
+        .or_else(|| non_empty_var("HOME").map(|dir| Path::new(&dir).join(".config/gh")))?;
+    Some(config_dir.join("hosts.yml"))
+}
+
+/// The `oauth_token` of `host` in the `hosts.yml` of the gh CLI.
+///
+/// Only the top-level host keys and their direct `oauth_token` member are read,
+/// a token stored in the system keyring is not available.
+fn gh_hosts_token<'r>(raw: &'r str, host: &str) -> Option<&'r str> {
+    let mut in_host = false;
+    let mut member_indent = None;
+    for line in raw.lines() {
+        let content = line.trim_start();
+        if content.is_empty() || content.starts_with('#') {
+            continue;
+        }
+        let indent = line.len() - content.len();
+        if indent == 0 {
+            in_host = content.strip_suffix(':').map(unquote) == Some(host);
+            member_indent = None;
+            continue;
+        }
+        if !in_host || *member_indent.get_or_insert(indent) != indent {
+            continue;
+        }
+        if let Some(value) = content.strip_prefix("oauth_token:") {
+            return Some(unquote(value.trim())).filter(|token| !token.is_empty());
+        }
+    }
+    None
+}
+
+fn unquote(value: &str) -> &str {
+    value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .or_else(|| {
+            value
+                .strip_prefix('\'')
+                .and_then(|value| value.strip_suffix('\''))
+        })
+        .unwrap_or(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rstest::rstest;
+
+    const GH_HOSTS: &str = r#"
+github.com:
+    users:
+        octocat:
+            oauth_token: gho_user
+    git_protocol: https
+    user: octocat
+    oauth_token: gho_public
+"github.example.com":
+    oauth_token: 'gho_enterprise'
+keyring.example.com:
+    user: octocat
+"#;
+
+    #[rstest]
+    #[case::github("github.com", Some("gho_public"))]
+    #[case::quoted("github.example.com", Some("gho_enterprise"))]
+    #[case::keyring("keyring.example.com", None)]
+    #[case::unknown("other.example.com", None)]
+    fn test_gh_hosts_token(#[case] host: &str, #[case] expected: Option<&str>) {
+        assert_eq!(gh_hosts_token(GH_HOSTS, host), expected);
+    }
+
+    #[rstest]
+    #[case::public("https://api.github.com", Some("github.com"))]
+    #[case::enterprise("https://github.example.com/api/v3", Some("github.example.com"))]
+    #[case::download(
+        "https://github.com/gohugoio/hugo/releases/download/v0.119.0/hugo.tar.gz",
+        Some("github.com")
+    )]
+    #[case::redirect(
+        "https://objects.githubusercontent.com/foo",
+        Some("objects.githubusercontent.com")
+    )]
+    fn test_credential_host(#[case] url: &str, #[case] expected: Option<&str>) {
+        let url = url.parse::<url::Url>().unwrap();
+
+        assert_eq!(credential_host(&url), expected);
+    }
+
+    #[rstest]
+    #[case::default_host("/run/secrets/token", "github.com", "/run/secrets/token")]
+    #[case::host(
+        "github.example.com=/run/secrets/ghe-token",
+        "github.example.com",
+        "/run/secrets/ghe-token"
+    )]
+    #[case::path_with_equal("./tokens/a=b", "github.com", "./tokens/a=b")]
+    fn test_parse_token_file(#[case] raw: &str, #[case] host: &str, #[case] path: &str) {
+        assert_eq!(
+            raw.parse::<GithubTokenFile>().unwrap(),
+            GithubTokenFile {
+                host: host.into(),
+                path: path.into(),
+            }
+        );
+    }
+}
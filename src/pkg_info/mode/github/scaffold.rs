@@ -0,0 +1,192 @@
+//! Guess the `arch_asset_patterns` of a GitHub release from common asset naming conventions.
+
+use std::collections::HashMap;
+
+use crate::pkg_info::Arch;
+
+use super::ArchAssetPattern;
+
+/// The pattern replacing the version in the generated regexes.
+const VERSION_PATTERN: &str = r"[0-9]+(?:\.[0-9]+)+";
+
+/// File extensions of assets that are not the package itself.
+const IGNORED_EXTENSIONS: [&str; 12] = [
+    ".asc", ".sig", ".pem", ".sha1", ".sha256", ".sha512", ".md5", ".sbom", ".json", ".txt",
+    ".deb", ".rpm",
+];
+
+/// Select a linux asset for each arch & turn its name into a version-agnostic regex.
+pub fn guess_arch_asset_patterns<'n>(
+    asset_names: impl IntoIterator<Item = &'n str>,
+    version: &str,
+) -> ArchAssetPattern {
+    let mut selected = HashMap::<Arch, &str>::new();
+
+    for name in asset_names {
+        let lower_name = name.to_ascii_lowercase();
+        if !lower_name.contains("linux")
+            || IGNORED_EXTENSIONS
+                .iter()
+                .any(|ext| lower_name.ends_with(ext))
+        {
+            continue;
+        }
+        let Some(arch) = guess_arch(&lower_name) else {
+            continue;
+        };
+        selected
+            .entry(arch)
+            .and_modify(|current| {
+                if (archive_rank(name), name.len()) < (archive_rank(current), current.len()) {
+                    *current = name;
+                }
+            })
+            .or_insert(name);
+    }
+
+    selected
+        .into_iter()
+        .map(|(arch, name)| {
+            let pattern = version_agnostic_pattern(name, version);
+            let regex = regex::Regex::new(&pattern).expect("Generated regex should be valid");
+            (arch, regex)
+        })
+        .collect()
+}
+
+fn guess_arch(lower_name: &str) -> Option<Arch> {
+    let tokens = lower_name
+        .split(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+        .flat_map(|token| [token].into_iter().chain(token.split('_')))
+        .collect::<Vec<_>>();
+    let has_token = |candidates: &[&str]| tokens.iter().any(|token| candidates.contains(token));
+
+    if has_token(&["x86_64", "amd64", "x64"]) {
+        Some(Arch::Amd64)
+    } else if has_token(&["aarch64", "arm64"]) {
+        Some(Arch::Arm64)
+    } else {
+        None
+    }
+}
+
+/// Prefer compressed archives, then plain binaries.
+fn archive_rank(name: &str) -> u8 {
+    let lower_name = name.to_ascii_lowercase();
+    if [".tar.gz", ".tgz"]
+        .iter()
+        .any(|ext| lower_name.ends_with(ext))
+    {
+        0
+    } else if [".tar.xz", ".tar.bz2", ".zip"]
+        .iter()
+        .any(|ext| lower_name.ends_with(ext))
+    {
+        1
+    } else if !lower_name.contains('.') {
+        2
+    } else {
+        3
+    }
+}
+
+/// Escape the asset name and replace the version with a generic pattern.
+///
+/// The first occurrence is captured as `version` (used by the `asset_captures` version source).
+fn version_agnostic_pattern(name: &str, version: &str) -> String {
+    let body = if !version.is_empty() && name.contains(version) {
+        let mut parts = name.split(version).map(regex::escape);
+        let first = parts.next().unwrap_or_default();
+        let rest = parts.collect::<Vec<_>>().join(VERSION_PATTERN);
+        format!("{first}(?<version>{VERSION_PATTERN}){rest}")
+    } else {
+        regex::escape(name)
+    };
+    format!("^{body}$")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::version_source::capture_version, *};
+
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::hugo(
+        &[
+            "hugo_0.119.0_checksums.txt",
+            "hugo_0.119.0_darwin-universal.tar.gz",
+            "hugo_0.119.0_linux-amd64.deb",
+            "hugo_0.119.0_linux-amd64.tar.gz",
+            "hugo_0.119.0_linux-arm64.tar.gz",
+            "hugo_extended_0.119.0_linux-amd64.tar.gz",
+            "hugo_0.119.0_windows-amd64.zip",
+        ],
+        "0.119.0",
+        &[
+            (Arch::Amd64, r"^hugo_(?<version>[0-9]+(?:\.[0-9]+)+)_linux\-amd64\.tar\.gz$"),
+            (Arch::Arm64, r"^hugo_(?<version>[0-9]+(?:\.[0-9]+)+)_linux\-arm64\.tar\.gz$"),
+        ]
+    )]
+    #[case::rust_target(
+        &[
+            "tool-v1.2.3-x86_64-unknown-linux-musl.tar.gz",
+            "tool-v1.2.3-x86_64-unknown-linux-musl.tar.gz.sha256",
+            "tool-v1.2.3-aarch64-unknown-linux-musl.tar.gz",
+            "tool-v1.2.3-x86_64-apple-darwin.tar.gz",
+        ],
+        "1.2.3",
+        &[
+            (Arch::Amd64, r"^tool\-v(?<version>[0-9]+(?:\.[0-9]+)+)\-x86_64\-unknown\-linux\-musl\.tar\.gz$"),
+            (Arch::Arm64, r"^tool\-v(?<version>[0-9]+(?:\.[0-9]+)+)\-aarch64\-unknown\-linux\-musl\.tar\.gz$"),
+        ]
+    )]
+    #[case::plain_binary(
+        &["argocd-darwin-amd64", "argocd-linux-amd64", "argocd-linux-arm64", "argocd-linux-ppc64le"],
+        "2.9.0",
+        &[
+            (Arch::Amd64, r"^argocd\-linux\-amd64$"),
+            (Arch::Arm64, r"^argocd\-linux\-arm64$"),
+        ]
+    )]
+    #[case::no_linux_asset(&["tool-windows-x64.zip"], "1.0.0", &[])]
+    fn test_guess_arch_asset_patterns(
+        #[case] asset_names: &[&str],
+        #[case] version: &str,
+        #[case] expected: &[(Arch, &str)],
+    ) {
+        let patterns = guess_arch_asset_patterns(asset_names.iter().copied(), version);
+        let mut got = patterns
+            .iter()
+            .map(|(arch, regex)| (*arch, regex.as_str()))
+            .collect::<Vec<_>>();
+        got.sort();
+
+        assert_eq!(got, expected);
+    }
+
+    #[rstest]
+    #[case::once(
+        "hugo_0.119.0_linux-amd64.tar.gz",
+        "0.119.0",
+        "hugo_0.120.1_linux-amd64.tar.gz",
+        "0.120.1"
+    )]
+    #[case::twice(
+        "tool-1.2.3/tool-1.2.3-linux-amd64",
+        "1.2.3",
+        "tool-1.10.0/tool-1.10.0-linux-amd64",
+        "1.10.0"
+    )]
+    fn generated_pattern_capture_version(
+        #[case] name: &str,
+        #[case] version: &str,
+        #[case] other_name: &str,
+        #[case] other_version: &str,
+    ) {
+        let pattern = regex::Regex::new(&version_agnostic_pattern(name, version)).unwrap();
+
+        assert_eq!(capture_version(&pattern, name), Some(version));
+        assert_eq!(capture_version(&pattern, other_name), Some(other_version));
+    }
+}
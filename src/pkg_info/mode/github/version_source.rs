@@ -0,0 +1,201 @@
+//! Where the version of a GitHub release is taken from.
+
+use std::borrow::Cow;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::{ArchAssetPattern, GithubRelease};
+
+/// Where the version of a release is taken from.
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum VersionSource {
+    /// The tag of the release.
+    TagName,
+    /// The title of the release.
+    #[default]
+    Name,
+    /// A capture of a regex applied to the tag or the title of the release.
+    Regex(VersionRegex),
+    /// The captures of the `arch_asset_patterns` regexes on the selected assets,
+    /// they must be the same for every arch.
+    AssetCaptures,
+}
+
+/// A regex extracting the version from a field of the release.
+///
+/// The version is the capture group named `version`, or the first one.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct VersionRegex {
+    /// The regex applied to the field.
+    #[serde(with = "crate::serde_utils::regex_str")]
+    #[schemars(with = "String", extend("format" = "regex"))]
+    pub pattern: regex::Regex,
+    /// The field of the release the regex is applied to.
+    #[serde(default)]
+    pub field: ReleaseField,
+}
+
+impl PartialEq for VersionRegex {
+    fn eq(&self, other: &Self) -> bool {
+        self.pattern.as_str() == other.pattern.as_str() && self.field == other.field
+    }
+}
+
+impl Eq for VersionRegex {}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Eq, Clone, Copy, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReleaseField {
+    #[default]
+    TagName,
+    Name,
+}
+
+impl VersionSource {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Extract the version of `release`.
+    pub(super) fn version_of<'r>(
+        &self,
+        release: &'r GithubRelease<'_>,
+        arch_asset_patterns: &ArchAssetPattern,
+    ) -> anyhow::Result<Cow<'r, str>> {
+        match self {
+            VersionSource::TagName => Ok(Cow::Borrowed(&release.tag_name)),
+            VersionSource::Name => Ok(Cow::Borrowed(&release.name)),
+            VersionSource::Regex(VersionRegex { pattern, field }) => {
+                let value = match field {
+                    ReleaseField::TagName => &release.tag_name,
+                    ReleaseField::Name => &release.name,
+                };
+                capture_version(pattern, value)
+                    .map(Cow::Borrowed)
+                    .ok_or_else(|| {
+                        anyhow::anyhow!("The version regex `{pattern}` does not match `{value}`")
+                    })
+            }
+            VersionSource::AssetCaptures => {
+                let mut captures = arch_asset_patterns
+                    .iter()
+                    .filter_map(|(arch, pattern)| {
+                        let asset = release
+                            .assets
+                            .iter()
+                            .find(|asset| pattern.is_match(&asset.name))?;
+                        Some((*arch, capture_version(pattern, &asset.name)))
+                    })
+                    .collect::<Vec<_>>();
+                captures.sort_by_key(|(arch, _)| *arch);
+
+                let Some((first_arch, first_version)) = captures.first() else {
+                    anyhow::bail!(
+                        "No asset matching `arch_asset_patterns` in release {}",
+                        release.name
+                    );
+                };
+                let version = first_version.ok_or_else(|| {
+                    anyhow::anyhow!("The pattern for {first_arch} has no capture group")
+                })?;
+                if captures.iter().any(|(_, other)| *other != Some(version)) {
+                    let found = captures
+                        .iter()
+                        .map(|(arch, version)| format!("{arch}={}", version.unwrap_or("none")))
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    anyhow::bail!("The versions captured from the assets differ: {found}");
+                }
+                Ok(Cow::Owned(version.to_owned()))
+            }
+        }
+    }
+}
+
+/// The capture group named `version` or the first one.
+pub(super) fn capture_version<'h>(pattern: &regex::Regex, haystack: &'h str) -> Option<&'h str> {
+    let captures = pattern.captures(haystack)?;
+    captures
+        .name("version")
+        .or_else(|| captures.get(1))
+        .map(|capture| capture.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pkg_info::Arch;
+
+    use super::*;
+
+    use rstest::rstest;
+
+    const RELEASE: &str = r#"{
+        "name": "Hugo v0.119.0 – The Big One",
+        "tag_name": "v0.119.0",
+        "html_url": "https://github.com/gohugoio/hugo/releases/tag/v0.119.0",
+        "prerelease": false,
+        "draft": false,
+        "assets": [
+            { "name": "hugo_0.119.0_linux-amd64.tar.gz", "size": 1, "browser_download_url": "https://example.com/amd64" },
+            { "name": "hugo_0.119.1_linux-arm64.tar.gz", "size": 1, "browser_download_url": "https://example.com/arm64" }
+        ]
+    }"#;
+
+    fn patterns(patterns: &[(Arch, &str)]) -> ArchAssetPattern {
+        patterns
+            .iter()
+            .map(|(arch, pattern)| (*arch, regex::Regex::new(pattern).unwrap()))
+            .collect()
+    }
+
+    #[rstest]
+    #[case::tag_name(r#""tag_name""#, "v0.119.0")]
+    #[case::name(r#""name""#, "Hugo v0.119.0 – The Big One")]
+    #[case::regex_on_name(r#"{ "regex": { "pattern": "v(\\S+)", "field": "name" } }"#, "0.119.0")]
+    #[case::regex_named_group(r#"{ "regex": { "pattern": "(v)?(?<version>.+)" } }"#, "0.119.0")]
+    #[case::asset_captures(r#""asset_captures""#, "0.119.0")]
+    fn test_version_of(#[case] raw_source: &str, #[case] expected: &str) {
+        let source = serde_json::from_str::<VersionSource>(raw_source).unwrap();
+        let release = serde_json::from_str::<GithubRelease>(RELEASE).unwrap();
+        let patterns = patterns(&[(Arch::Amd64, r"^hugo_([0-9.]+)_linux-amd64\.tar\.gz$")]);
+
+        assert_eq!(source.version_of(&release, &patterns).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case::regex_no_match(
+        r#"{ "regex": { "pattern": "^release-(.+)$" } }"#,
+        &[(Arch::Amd64, r"^hugo_([0-9.]+)_linux-amd64\.tar\.gz$")],
+        "The version regex `^release-(.+)$` does not match `v0.119.0`"
+    )]
+    #[case::captures_differ(
+        r#""asset_captures""#,
+        &[
+            (Arch::Amd64, r"^hugo_([0-9.]+)_linux-amd64\.tar\.gz$"),
+            (Arch::Arm64, r"^hugo_([0-9.]+)_linux-arm64\.tar\.gz$"),
+        ],
+        "The versions captured from the assets differ: amd64=0.119.0, arm64=0.119.1"
+    )]
+    #[case::no_capture_group(
+        r#""asset_captures""#,
+        &[(Arch::Amd64, r"^hugo_[0-9.]+_linux-amd64\.tar\.gz$")],
+        "The pattern for amd64 has no capture group"
+    )]
+    fn test_version_of_error(
+        #[case] raw_source: &str,
+        #[case] patterns_: &[(Arch, &str)],
+        #[case] expected: &str,
+    ) {
+        let source = serde_json::from_str::<VersionSource>(raw_source).unwrap();
+        let release = serde_json::from_str::<GithubRelease>(RELEASE).unwrap();
+
+        let err = source
+            .version_of(&release, &patterns(patterns_))
+            .unwrap_err();
+
+        assert_eq!(err.to_string(), expected);
+    }
+}
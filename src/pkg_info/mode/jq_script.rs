@@ -2,17 +2,21 @@ use std::{path::Path, process::Stdio};
 
 use anyhow::Context;
 use futures::TryStreamExt;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use tokio::process::Command;
 
-use crate::{ModeGetLatestVersion, PkgOption};
+use crate::{pkg_info::PrereleasePolicy, ModeGetLatestVersion, PkgOption};
 
-use super::VersionComponent;
+use super::{ModeContext, VersionComponent};
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
 #[serde(rename_all = "kebab-case")]
+#[schemars(rename = "JqScriptReleaseHandler")]
 pub struct ReleaseHandler<'a> {
+    /// The URL of the JSON document to provide to the jq script.
     pub document_url: url::Url,
+    /// The path to the jq script printing the latest version info.
     #[serde(borrow)]
     pub script_path: &'a Path,
 }
@@ -21,17 +25,42 @@ impl<'a> ModeGetLatestVersion for ReleaseHandler<'a> {
     async fn get_latest_version(
         &self,
         option: &PkgOption,
-        _tmp_dir: &Path,
-        _in_test_mode: bool,
+        context: ModeContext<'_>,
     ) -> anyhow::Result<VersionComponent> {
-        let http_client = crate::reqwest_utils::prepare_http_client_json()
-            .build()
-            .context("Failed to build http client")?;
+        self.run(None, option, context).await
+    }
+
+    async fn get_version(
+        &self,
+        version: &str,
+        option: &PkgOption,
+        context: ModeContext<'_>,
+    ) -> anyhow::Result<VersionComponent> {
+        self.run(Some(version), option, context).await
+    }
+}
 
+impl<'a> ReleaseHandler<'a> {
+    /// Run the jq script, the requested version is available in `$ENV.REQUESTED_VERSION`,
+    /// the version constraint in `$ENV.VERSION_CONSTRAINT` and the prerelease policy in `$ENV.PRERELEASE_POLICY`.
+    async fn run(
+        &self,
+        requested_version: Option<&str>,
+        option: &PkgOption,
+        context: ModeContext<'_>,
+    ) -> anyhow::Result<VersionComponent> {
         let mut cmd = Command::new("jq");
-        if option.allow_prerelease {
+        let prerelease_policy = option.prerelease_policy();
+        if prerelease_policy != PrereleasePolicy::StableOnly {
             cmd.env("ALLOW_PRERELEASE", "1");
         }
+        cmd.env("PRERELEASE_POLICY", prerelease_policy.as_str());
+        if let Some(version) = requested_version {
+            cmd.env("REQUESTED_VERSION", version);
+        }
+        if let Some(constraint) = &option.version_constraint {
+            cmd.env("VERSION_CONSTRAINT", constraint.to_string());
+        }
         cmd.arg("--from-file")
             .arg(self.script_path)
             .stdin(Stdio::piped())
@@ -39,7 +68,11 @@ impl<'a> ModeGetLatestVersion for ReleaseHandler<'a> {
             .stderr(Stdio::inherit());
 
         log::info!("Requesting json document ...");
-        let document_resp = http_client.get(self.document_url.clone()).send().await?;
+        let document_resp = context
+            .http_client
+            .get(self.document_url.clone())
+            .send()
+            .await?;
         anyhow::ensure!(
             document_resp.status() == reqwest::StatusCode::OK,
             "Invalid response status"
@@ -63,6 +96,7 @@ impl<'a> ModeGetLatestVersion for ReleaseHandler<'a> {
         drop(stdin);
 
         log::trace!("Streamed {} bytes to jq's stdin", bytes_streamed);
+        context.record_download(bytes_streamed);
 
         log::info!("Waiting for jq to finish ...");
         let output = process.wait_with_output().await?;
@@ -0,0 +1,99 @@
+use std::{collections::HashSet, num::NonZeroUsize};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::version;
+
+/// Which recorded versions are kept when pruning the `versions` map.
+///
+/// A version is kept when it is selected by any of the rules,
+/// the `latest_version` is always kept.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Default, Clone, Copy, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct Retention {
+    /// Keep the N most recent versions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keep_last: Option<NonZeroUsize>,
+    /// Keep the most recent version of each major version.
+    #[serde(default)]
+    pub keep_latest_per_major: bool,
+}
+
+impl Retention {
+    /// Select the versions to remove, sorted from the oldest to the most recent.
+    pub fn select_pruned<'v>(
+        &self,
+        versions: impl IntoIterator<Item = &'v str>,
+        latest_version: Option<&str>,
+    ) -> Vec<&'v str> {
+        if self.keep_last.is_none() && !self.keep_latest_per_major {
+            return Vec::new();
+        }
+
+        let mut versions = versions.into_iter().collect::<Vec<_>>();
+        versions.sort_unstable_by(|a, b| version::compare(b, a));
+
+        let mut seen_majors = HashSet::new();
+        let mut pruned = versions
+            .into_iter()
+            .enumerate()
+            .filter(|(index, version)| {
+                let in_last = self.keep_last.is_some_and(|n| *index < n.get());
+                let latest_of_major =
+                    self.keep_latest_per_major && seen_majors.insert(version::major(version));
+                let is_latest = latest_version == Some(*version);
+                !(in_last || latest_of_major || is_latest)
+            })
+            .map(|(_, version)| version)
+            .collect::<Vec<_>>();
+        pruned.reverse();
+        pruned
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rstest::rstest;
+
+    const VERSIONS: [&str; 7] = [
+        "0.9.0", "1.0.0", "1.2.0", "1.10.0", "2.0.0", "2.0.1", "2.1.0",
+    ];
+
+    #[rstest]
+    #[case::no_rule(None, false, Some("2.1.0"), &[])]
+    #[case::keep_last(
+        NonZeroUsize::new(3),
+        false,
+        Some("2.1.0"),
+        &["0.9.0", "1.0.0", "1.2.0", "1.10.0"]
+    )]
+    #[case::keep_latest_per_major(
+        None,
+        true,
+        Some("2.1.0"),
+        &["1.0.0", "1.2.0", "2.0.0", "2.0.1"]
+    )]
+    #[case::both_rules(NonZeroUsize::new(2), true, Some("2.1.0"), &["1.0.0", "1.2.0", "2.0.0"])]
+    #[case::keep_older_latest(
+        NonZeroUsize::new(1),
+        false,
+        Some("1.2.0"),
+        &["0.9.0", "1.0.0", "1.10.0", "2.0.0", "2.0.1"]
+    )]
+    fn test_select_pruned(
+        #[case] keep_last: Option<NonZeroUsize>,
+        #[case] keep_latest_per_major: bool,
+        #[case] latest_version: Option<&str>,
+        #[case] expected: &[&str],
+    ) {
+        let retention = Retention {
+            keep_last,
+            keep_latest_per_major,
+        };
+
+        assert_eq!(retention.select_pruned(VERSIONS, latest_version), expected);
+    }
+}
@@ -0,0 +1,201 @@
+//! Render the Markdown body of a pull-request bumping a package.
+
+use std::fmt::Display;
+
+use crate::{
+    pkg_info::{ReleaseNotes, VersionContent},
+    report::{diff_arches, ArchChange},
+    ExtractError, PkgInfo,
+};
+
+/// The body of a pull-request going from the `latest_version` of an old pkg-info to a new one.
+#[derive(Debug)]
+pub struct PrBody<'a> {
+    name: &'a str,
+    old_version: Option<&'a str>,
+    new_version: &'a str,
+    changes: Vec<ArchChange>,
+    release: Option<ReleaseNotes>,
+}
+
+impl<'a> PrBody<'a> {
+    pub fn new(
+        old: &'a PkgInfo<'_>,
+        new: &'a PkgInfo<'_>,
+        release: Option<ReleaseNotes>,
+    ) -> Result<Self, ExtractError> {
+        let new_version = new
+            .base
+            .latest_version
+            .as_deref()
+            .ok_or(ExtractError::MissingLatestVersion)?;
+        let new_content = latest_content(new)
+            .ok_or_else(|| ExtractError::UnknownVersion(new_version.to_owned()))?;
+
+        Ok(Self {
+            name: new.base.name,
+            old_version: old.base.latest_version.as_deref(),
+            new_version,
+            changes: diff_arches(latest_content(old), new_content),
+            release,
+        })
+    }
+}
+
+fn latest_content<'a>(pkg_info: &'a PkgInfo<'_>) -> Option<&'a VersionContent<'a>> {
+    let version = pkg_info.base.latest_version.as_deref()?;
+    pkg_info.base.versions.as_ref()?.get(version)
+}
+
+impl Display for PrBody<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.old_version {
+            Some(old_version) if old_version == self.new_version => writeln!(
+                f,
+                "Update the assets of {} `{}`.",
+                self.name, self.new_version
+            )?,
+            Some(old_version) => writeln!(
+                f,
+                "Bump {} from `{old_version}` to `{}`.",
+                self.name, self.new_version
+            )?,
+            None => writeln!(f, "Add {} `{}`.", self.name, self.new_version)?,
+        }
+
+        writeln!(f)?;
+        writeln!(f, "| Arch | Asset | Digest | Change |")?;
+        writeln!(f, "| ---- | ----- | ------ | ------ |")?;
+        for change in &self.changes {
+            let Some(entry) = change.current.as_ref().or(change.previous.as_ref()) else {
+                continue;
+            };
+            writeln!(
+                f,
+                "| {} | [{}]({}) | `{}` | {} |",
+                change.arch, entry.filename, entry.download_url, entry.digest, change.kind
+            )?;
+        }
+
+        if let Some(release) = &self.release {
+            writeln!(f)?;
+            writeln!(f, "[Upstream release]({})", release.url)?;
+            if let Some(body) = &release.body {
+                writeln!(f)?;
+                writeln!(f, "<details>")?;
+                writeln!(f, "<summary>Release notes</summary>")?;
+                writeln!(f)?;
+                writeln!(f, "{}", body.trim_end())?;
+                writeln!(f)?;
+                writeln!(f, "</details>")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OLD: &str = r#"{
+        "name": "Foo",
+        "latest_version": "1.0.0",
+        "versions": {
+            "1.0.0": {
+                "amd64": {
+                    "filename": "foo-amd64",
+                    "download_url": "https://example.com/1.0.0/foo-amd64",
+                    "digest": "sha256:aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa"
+                }
+            }
+        },
+        "mode": "github-release",
+        "repository_path": "example/foo",
+        "arch_asset_patterns": { "amd64": "^foo-amd64$", "arm64": "^foo-arm64$" }
+    }"#;
+
+    const NEW: &str = r#"{
+        "name": "Foo",
+        "latest_version": "1.1.0",
+        "versions": {
+            "1.0.0": {
+                "amd64": {
+                    "filename": "foo-amd64",
+                    "download_url": "https://example.com/1.0.0/foo-amd64",
+                    "digest": "sha256:aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa"
+                }
+            },
+            "1.1.0": {
+                "amd64": {
+                    "filename": "foo-amd64",
+                    "download_url": "https://example.com/1.1.0/foo-amd64",
+                    "digest": "sha256:bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb"
+                },
+                "arm64": {
+                    "filename": "foo-arm64",
+                    "download_url": "https://example.com/1.1.0/foo-arm64",
+                    "digest": "sha256:cccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccc"
+                }
+            }
+        },
+        "mode": "github-release",
+        "repository_path": "example/foo",
+        "arch_asset_patterns": { "amd64": "^foo-amd64$", "arm64": "^foo-arm64$" }
+    }"#;
+
+    #[test]
+    fn render_version_bump() {
+        let old = serde_json::from_str::<PkgInfo>(OLD).unwrap();
+        let new = serde_json::from_str::<PkgInfo>(NEW).unwrap();
+        let release = ReleaseNotes {
+            url: "https://github.com/example/foo/releases/tag/v1.1.0"
+                .parse()
+                .unwrap(),
+            body: Some("- Fix things\n".into()),
+        };
+
+        let body = PrBody::new(&old, &new, Some(release)).unwrap();
+
+        assert_eq!(
+            body.to_string(),
+            "\
+Bump Foo from `1.0.0` to `1.1.0`.
+
+| Arch | Asset | Digest | Change |
+| ---- | ----- | ------ | ------ |
+| amd64 | [foo-amd64](https://example.com/1.1.0/foo-amd64) | `sha256:bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb` | changed |
+| arm64 | [foo-arm64](https://example.com/1.1.0/foo-arm64) | `sha256:cccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccc` | added |
+
+[Upstream release](https://github.com/example/foo/releases/tag/v1.1.0)
+
+<details>
+<summary>Release notes</summary>
+
+- Fix things
+
+</details>
+"
+        );
+    }
+
+    #[test]
+    fn render_assets_update_without_release() {
+        let new = serde_json::from_str::<PkgInfo>(NEW).unwrap();
+
+        let body = PrBody::new(&new, &new, None).unwrap();
+
+        assert_eq!(
+            body.to_string(),
+            "\
+Update the assets of Foo `1.1.0`.
+
+| Arch | Asset | Digest | Change |
+| ---- | ----- | ------ | ------ |
+| amd64 | [foo-amd64](https://example.com/1.1.0/foo-amd64) | `sha256:bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb` | unchanged |
+| arm64 | [foo-arm64](https://example.com/1.1.0/foo-arm64) | `sha256:cccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccc` | unchanged |
+"
+        );
+    }
+}
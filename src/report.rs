@@ -0,0 +1,257 @@
+//! Machine-readable report of an `update` or `check` run.
+
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+};
+
+use serde::Serialize;
+
+use crate::{
+    pkg_info::{Arch, CheckOutcome, VersionContent, VersionedArchEntry},
+    PkgInfo,
+};
+
+/// The report of a run over one or more pkg info files.
+#[derive(Debug, Serialize)]
+pub struct RunReport<'a> {
+    /// The executed command.
+    pub command: &'static str,
+    /// The packages that were successfully processed.
+    pub packages: Vec<&'a PackageReport>,
+    /// The packages that failed to be processed.
+    pub failures: Vec<PackageFailure<'a>>,
+    /// The total number of bytes downloaded.
+    pub downloaded_bytes: u64,
+    /// The duration of the whole run in milliseconds.
+    pub duration_ms: u64,
+}
+
+impl<'a> RunReport<'a> {
+    pub fn new(
+        command: &'static str,
+        results: impl IntoIterator<Item = (&'a Path, &'a Result<PackageReport, anyhow::Error>)>,
+        duration_ms: u64,
+    ) -> Self {
+        let mut packages = Vec::new();
+        let mut failures = Vec::new();
+        for (file, result) in results {
+            match result {
+                Ok(package) => packages.push(package),
+                Err(e) => failures.push(PackageFailure {
+                    file,
+                    error: format!("{e:#}"),
+                }),
+            }
+        }
+        let downloaded_bytes = packages
+            .iter()
+            .map(|package| package.downloaded_bytes)
+            .sum();
+
+        Self {
+            command,
+            packages,
+            failures,
+            downloaded_bytes,
+            duration_ms,
+        }
+    }
+}
+
+/// A package that failed to be processed.
+#[derive(Debug, Serialize)]
+pub struct PackageFailure<'a> {
+    pub file: &'a Path,
+    pub error: String,
+}
+
+/// What happened to a single package.
+#[derive(Debug, Serialize)]
+pub struct PackageReport {
+    /// The path to the pkg info file.
+    pub file: PathBuf,
+    pub name: String,
+    /// The mode used to retrieve the version.
+    pub mode: &'static str,
+    pub outcome: CheckOutcome,
+    /// The `latest_version` recorded before the run.
+    pub previous_version: Option<String>,
+    /// The latest version found upstream.
+    pub new_version: String,
+    /// The changes of the assets compared to the previous `latest_version`.
+    pub changes: Vec<ArchChange>,
+    /// The number of bytes downloaded to retrieve the version.
+    pub downloaded_bytes: u64,
+    pub timings: Timings,
+}
+
+impl PackageReport {
+    /// Prepare the report of a package, `content` is the one of `new_version`.
+    pub fn new(
+        file: &Path,
+        pkg_info: &PkgInfo<'_>,
+        new_version: &str,
+        content: &VersionContent<'_>,
+    ) -> Self {
+        let previous_version = pkg_info.base.latest_version.as_deref();
+        let previous_content = previous_version.and_then(|version| {
+            pkg_info
+                .base
+                .versions
+                .as_ref()
+                .and_then(|versions| versions.get(version))
+        });
+
+        Self {
+            file: file.to_owned(),
+            name: pkg_info.base.name.to_owned(),
+            mode: pkg_info.mode.name(),
+            outcome: pkg_info.base.check_version(new_version, content),
+            previous_version: previous_version.map(Into::into),
+            new_version: new_version.to_owned(),
+            changes: diff_arches(previous_content, content),
+            downloaded_bytes: 0,
+            timings: Timings::default(),
+        }
+    }
+}
+
+impl Display for PackageReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {} ({} -> {})",
+            self.name,
+            self.outcome,
+            self.previous_version.as_deref().unwrap_or("none"),
+            self.new_version
+        )
+    }
+}
+
+/// The time spent processing a package, in milliseconds.
+#[derive(Debug, Serialize, Default, Clone, Copy)]
+pub struct Timings {
+    /// Time spent retrieving the version from upstream.
+    pub resolve_ms: u64,
+    /// Time spent processing the package.
+    pub total_ms: u64,
+}
+
+#[derive(Debug, Serialize, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "kebab-case")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+    Unchanged,
+}
+
+impl Display for ChangeKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ChangeKind::Added => "added",
+            ChangeKind::Removed => "removed",
+            ChangeKind::Changed => "changed",
+            ChangeKind::Unchanged => "unchanged",
+        })
+    }
+}
+
+/// The change of the asset of an arch.
+#[derive(Debug, Serialize, PartialEq, Eq)]
+pub struct ArchChange {
+    pub arch: Arch,
+    pub kind: ChangeKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous: Option<VersionedArchEntry<'static>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub current: Option<VersionedArchEntry<'static>>,
+}
+
+/// Compare the assets of each arch.
+pub fn diff_arches(
+    previous: Option<&VersionContent<'_>>,
+    current: &VersionContent<'_>,
+) -> Vec<ArchChange> {
+    Arch::ALL
+        .into_iter()
+        .filter_map(|arch| {
+            let previous = previous.and_then(|content| content.get(&arch));
+            let current = current.get(&arch);
+            let kind = match (previous, current) {
+                (None, None) => return None,
+                (None, Some(_)) => ChangeKind::Added,
+                (Some(_), None) => ChangeKind::Removed,
+                (Some(previous), Some(current)) if previous == current => ChangeKind::Unchanged,
+                (Some(_), Some(_)) => ChangeKind::Changed,
+            };
+            Some(ArchChange {
+                arch,
+                kind,
+                previous: previous.map(VersionedArchEntry::to_owned),
+                current: current.map(VersionedArchEntry::to_owned),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use super::*;
+    use crate::Digest;
+
+    fn entry(filename: &'static str) -> VersionedArchEntry<'static> {
+        VersionedArchEntry {
+            filename: Cow::Borrowed(filename),
+            download_url: format!("https://example.com/{filename}").parse().unwrap(),
+            digest: Digest::Sha256(Cow::Borrowed("aa")),
+        }
+    }
+
+    fn kinds(changes: &[ArchChange]) -> Vec<(Arch, ChangeKind)> {
+        changes
+            .iter()
+            .map(|change| (change.arch, change.kind))
+            .collect()
+    }
+
+    #[test]
+    fn test_diff_arches() {
+        let previous = [(Arch::Amd64, entry("foo-1.0-amd64"))]
+            .into_iter()
+            .collect::<VersionContent>();
+        let current = [
+            (Arch::Amd64, entry("foo-1.1-amd64")),
+            (Arch::Arm64, entry("foo-1.1-arm64")),
+        ]
+        .into_iter()
+        .collect::<VersionContent>();
+
+        assert_eq!(
+            kinds(&diff_arches(Some(&previous), &current)),
+            [
+                (Arch::Amd64, ChangeKind::Changed),
+                (Arch::Arm64, ChangeKind::Added)
+            ]
+        );
+        assert_eq!(
+            kinds(&diff_arches(Some(&current), &previous)),
+            [
+                (Arch::Amd64, ChangeKind::Changed),
+                (Arch::Arm64, ChangeKind::Removed)
+            ]
+        );
+        assert_eq!(
+            kinds(&diff_arches(Some(&previous), &previous)),
+            [(Arch::Amd64, ChangeKind::Unchanged)]
+        );
+        assert_eq!(
+            kinds(&diff_arches(None, &previous)),
+            [(Arch::Amd64, ChangeKind::Added)]
+        );
+    }
+}
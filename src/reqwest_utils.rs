@@ -3,6 +3,8 @@ use reqwest::{
     ClientBuilder,
 };
 
+use crate::pkg_info::{Digest, DigestAlgorithm};
+
 pub fn prepare_http_client_json() -> ClientBuilder {
     ClientBuilder::default()
         .gzip(true)
@@ -11,3 +13,57 @@ pub fn prepare_http_client_json() -> ClientBuilder {
             (ACCEPT, HeaderValue::from_static("application/json")),
         ]))
 }
+
+/// A client downloading the assets as-is, without decompressing them.
+///
+/// It is used both to record the digest of the assets and to verify them, so both hash the same bytes.
+pub fn prepare_http_client_download() -> ClientBuilder {
+    ClientBuilder::default()
+        .no_gzip()
+        .default_headers(HeaderMap::from_iter([(
+            USER_AGENT,
+            HeaderValue::from_static("pkg-info-updater"),
+        )]))
+}
+
+/// Stream the response body into a hasher, returning the downloaded size and its digest.
+pub async fn digest_response(
+    response: reqwest::Response,
+    algorithm: DigestAlgorithm,
+) -> reqwest::Result<(usize, Digest<'static>)> {
+    use futures::TryStreamExt;
+
+    let (size, hasher) = response
+        .bytes_stream()
+        .try_fold(
+            (0_usize, algorithm.hasher()),
+            |(size, mut hasher), chunk| async move {
+                hasher.update(&chunk);
+                Ok((size + chunk.len(), hasher))
+            },
+        )
+        .await?;
+
+    Ok((size, hasher.finalize()))
+}
+
+/// Stream the response body into `writer` while hashing it, returning the downloaded size and its digest.
+pub async fn download_response(
+    response: reqwest::Response,
+    algorithm: DigestAlgorithm,
+    writer: &mut impl std::io::Write,
+) -> std::io::Result<(usize, Digest<'static>)> {
+    use futures::TryStreamExt;
+
+    let mut stream = response.bytes_stream().map_err(std::io::Error::other);
+    let mut size = 0;
+    let mut hasher = algorithm.hasher();
+    while let Some(chunk) = stream.try_next().await? {
+        hasher.update(&chunk);
+        writer.write_all(&chunk)?;
+        size += chunk.len();
+    }
+    writer.flush()?;
+
+    Ok((size, hasher.finalize()))
+}
@@ -0,0 +1,90 @@
+//! Generate the JSON schema of a pkg-info file from the rust types.
+
+use schemars::{
+    generate::SchemaSettings,
+    transform::{RecursiveTransform, Transform},
+    Schema,
+};
+use serde_json::{json, Value};
+
+use crate::PkgInfo;
+
+/// Generate the JSON schema of [`PkgInfo`].
+pub fn generate() -> Schema {
+    let mut schema = SchemaSettings::draft2020_12()
+        .into_generator()
+        .into_root_schema_for::<PkgInfo>();
+
+    RecursiveTransform(remove_null_type).transform(&mut schema);
+    schema
+}
+
+/// Generate the JSON schema of [`PkgInfo`] as it is written in `pkg-info.schema.json`.
+pub fn generate_pretty() -> String {
+    let mut raw_schema =
+        serde_json::to_string_pretty(&generate()).expect("Schema is always serializable");
+    raw_schema.push('\n');
+    raw_schema
+}
+
+/// Optional fields are skipped when missing, so do not advertise `null` as a valid value.
+fn remove_null_type(schema: &mut Schema) {
+    let null_type = json!({ "type": "null" });
+
+    if let Some(Value::Array(types)) = schema.get_mut("type") {
+        types.retain(|ty| ty != "null");
+        if let [ty] = types.as_slice() {
+            let ty = ty.clone();
+            schema.insert("type".into(), ty);
+        }
+    }
+    if let Some(Value::Array(any_of)) = schema.get("anyOf") {
+        if let [subschema, other] = any_of.as_slice() {
+            if *other == null_type {
+                if let Value::Object(subschema) = subschema.clone() {
+                    schema.remove("anyOf");
+                    schema.ensure_object().extend(subschema);
+                }
+            }
+        }
+    }
+}
+
+/// Replace the `oneOf` generated for an internally tagged enum by `if`/`then` conditions on its tag.
+///
+/// When no variant of a `oneOf` match, a validator only report that, whereas with conditions
+/// it report the faulty fields of the variant selected by the tag.
+pub(crate) fn tag_conditions(schema: &mut Schema) {
+    const TAG: &str = "mode";
+
+    let Some(Value::Array(variants)) = schema.remove("oneOf") else {
+        return;
+    };
+    let mut tags = Vec::with_capacity(variants.len());
+    let conditions = variants
+        .into_iter()
+        .filter_map(|mut variant| {
+            let tag = variant
+                .pointer(&format!("/properties/{TAG}/const"))?
+                .clone();
+            let variant_obj = variant.as_object_mut()?;
+            variant_obj.remove("properties");
+            variant_obj.remove("required");
+            tags.push(tag.clone());
+            Some(json!({
+                "if": {
+                    "properties": { TAG: { "const": tag } },
+                    "required": [TAG]
+                },
+                "then": variant
+            }))
+        })
+        .collect::<Vec<_>>();
+
+    schema.insert(
+        "properties".into(),
+        json!({ TAG: { "type": "string", "enum": tags } }),
+    );
+    schema.insert("required".into(), json!([TAG]));
+    schema.insert("allOf".into(), Value::Array(conditions));
+}
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::{borrow::Cow, collections::HashMap};
 
 pub fn ordered_map<S, K, V>(value: &HashMap<K, V>, serializer: S) -> Result<S::Ok, S::Error>
 where
@@ -10,3 +10,43 @@ where
 
     serializer.collect_map(value.iter().sorted_by_key(|(k, _v)| *k))
 }
+
+/// Serialize a map keyed by versions in version order.
+pub fn version_ordered_map<S, V>(
+    value: &HashMap<Cow<'_, str>, V>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+    V: serde::Serialize,
+{
+    use itertools::Itertools;
+
+    serializer.collect_map(
+        value
+            .iter()
+            .sorted_by(|(a, _), (b, _)| crate::version::sort_cmp(a, b)),
+    )
+}
+
+/// (De)serialize a regex as its pattern.
+pub mod regex_str {
+    use std::borrow::Cow;
+
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S>(regex: &regex::Regex, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(regex.as_str())
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<regex::Regex, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let raw = Cow::<str>::deserialize(deserializer)?;
+        regex::Regex::new(&raw).map_err(D::Error::custom)
+    }
+}
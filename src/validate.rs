@@ -0,0 +1,250 @@
+//! Validate a pkg-info file against the embedded JSON schema and the rust types.
+
+use std::{collections::HashMap, fmt::Display};
+
+use serde_json::Value;
+
+use crate::{
+    json_edit::{parse_spans, Node, NodeKind},
+    PkgInfo,
+};
+
+/// The JSON schema of a pkg-info file.
+pub const SCHEMA: &str = include_str!("../pkg-info.schema.json");
+
+/// A problem found in a pkg-info file.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Problem {
+    /// The JSON pointer to the faulty value (empty for the document root).
+    pub path: String,
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let path = if self.path.is_empty() {
+            "(root)"
+        } else {
+            &self.path
+        };
+        write!(f, "{}:{}: {path}: {}", self.line, self.column, self.message)
+    }
+}
+
+/// Validate pkg-info documents, reporting every problem found.
+pub struct Validator {
+    schema: jsonschema::Validator,
+}
+
+impl Validator {
+    pub fn new() -> anyhow::Result<Self> {
+        let schema = serde_json::from_str::<Value>(SCHEMA)?;
+        let schema = jsonschema::options()
+            .should_validate_formats(true)
+            .build(&schema)
+            .map_err(|e| anyhow::anyhow!("Invalid embedded schema: {e}"))?;
+
+        Ok(Self { schema })
+    }
+
+    /// Validate the raw content of a pkg-info file.
+    pub fn validate(&self, raw: &str) -> Vec<Problem> {
+        let document = match serde_json::from_str::<Value>(raw) {
+            Ok(document) => document,
+            Err(e) => return vec![serde_problem(&e)],
+        };
+        let positions = index_positions(raw);
+        let problem_at = |path: String, message: String| {
+            let (line, column) = positions
+                .get(&path)
+                .map_or((1, 1), |offset| line_column(raw, *offset));
+            Problem {
+                path,
+                line,
+                column,
+                message,
+            }
+        };
+
+        let mut problems = self
+            .schema
+            .iter_errors(&document)
+            .map(|e| problem_at(e.instance_path().as_str().to_owned(), e.to_string()))
+            .collect::<Vec<_>>();
+
+        // The schema use ECMA 262 regexes, check them with the regex engine we use.
+        if let Some(Value::Object(patterns)) = document.get("arch_asset_patterns") {
+            for (arch, pattern) in patterns {
+                let path = format!("/arch_asset_patterns/{}", escape_pointer_token(arch));
+                let Some(Err(e)) = pattern.as_str().map(regex::Regex::new) else {
+                    continue;
+                };
+                if !problems.iter().any(|problem| problem.path == path) {
+                    problems.push(problem_at(path, format!("Invalid regex: {e}")));
+                }
+            }
+        }
+
+        if problems.is_empty() {
+            if let Err(e) = serde_json::from_str::<PkgInfo>(raw) {
+                problems.push(serde_problem(&e));
+            }
+        }
+
+        problems.sort_by_key(|problem| (problem.line, problem.column));
+        problems
+    }
+}
+
+fn serde_problem(error: &serde_json::Error) -> Problem {
+    Problem {
+        path: String::new(),
+        line: error.line(),
+        column: error.column(),
+        message: error.to_string(),
+    }
+}
+
+fn escape_pointer_token(token: &str) -> String {
+    token.replace('~', "~0").replace('/', "~1")
+}
+
+fn line_column(raw: &str, offset: usize) -> (usize, usize) {
+    let before = &raw[..offset];
+    let line = before.matches('\n').count() + 1;
+    let column = before
+        .rsplit('\n')
+        .next()
+        .map_or(0, |line| line.chars().count())
+        + 1;
+
+    (line, column)
+}
+
+/// Index the offset of every value of a valid JSON document by its JSON pointer.
+fn index_positions(raw: &str) -> HashMap<String, usize> {
+    let mut index = HashMap::new();
+
+    index_node(&parse_spans(raw), String::new(), &mut index);
+    index
+}
+
+fn index_node(node: &Node, pointer: String, index: &mut HashMap<String, usize>) {
+    index.insert(pointer.clone(), node.start);
+
+    match &node.kind {
+        NodeKind::Object(members) => {
+            for member in members {
+                let pointer = format!("{pointer}/{}", escape_pointer_token(&member.key));
+                index_node(&member.value, pointer, index);
+            }
+        }
+        NodeKind::Array(items) => {
+            for (i, item) in items.iter().enumerate() {
+                index_node(item, format!("{pointer}/{i}"), index);
+            }
+        }
+        NodeKind::Scalar => (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn index_json_positions() {
+        let raw = "{\n  \"a\": [1, {\"b/c\": \"d\"}],\n  \"e\\\"\": null\n}";
+
+        let index = index_positions(raw);
+
+        assert_eq!(line_column(raw, index[""]), (1, 1));
+        assert_eq!(line_column(raw, index["/a"]), (2, 8));
+        assert_eq!(line_column(raw, index["/a/0"]), (2, 9));
+        assert_eq!(line_column(raw, index["/a/1/b~1c"]), (2, 20));
+        assert_eq!(line_column(raw, index["/e\""]), (3, 10));
+    }
+
+    #[test]
+    fn report_every_problem() {
+        let raw = r#"{
+  "name": "Foobar",
+  "mode": "github-release",
+  "repository_path": "foo/bar",
+  "arch_asset_patterns": {
+    "amd64": "^foo(?=bar)$"
+  },
+  "versions": {
+    "1.0.0": {
+      "amd64": {
+        "filename": "foo",
+        "download_url": "https://example.com/foo",
+        "digest": "sha512:abcd"
+      }
+    }
+  }
+}"#;
+        let validator = Validator::new().unwrap();
+
+        let problems = validator.validate(raw);
+
+        assert_eq!(
+            problems
+                .iter()
+                .map(|problem| (problem.path.as_str(), problem.line, problem.column))
+                .collect::<Vec<_>>(),
+            [
+                ("/arch_asset_patterns/amd64", 6, 14),
+                ("/versions/1.0.0/amd64/digest", 13, 19),
+            ]
+        );
+    }
+
+    #[test]
+    fn report_missing_mode_field() {
+        let raw = r#"{ "name": "Foobar", "mode": "bash-command" }"#;
+        let validator = Validator::new().unwrap();
+
+        let problems = validator.validate(raw);
+
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].path, "");
+        assert!(
+            problems[0].message.contains("command"),
+            "{}",
+            problems[0].message
+        );
+    }
+
+    #[test]
+    fn report_unknown_field_outside_extensions() {
+        let raw = r#"{ "name": "Foobar", "mode": "bash-command", "command": "true", "x-owner": "infra", "owner": "infra" }"#;
+        let validator = Validator::new().unwrap();
+
+        let problems = validator.validate(raw);
+
+        assert_eq!(problems.len(), 1);
+        assert!(
+            problems[0].message.contains("'owner'") && !problems[0].message.contains("x-owner"),
+            "{}",
+            problems[0].message
+        );
+    }
+
+    #[test]
+    fn report_syntax_error() {
+        let validator = Validator::new().unwrap();
+
+        let problems = validator.validate("{\n  \"name\": \"Foobar\",\n}");
+
+        assert_eq!(
+            problems
+                .iter()
+                .map(|problem| (problem.line, problem.column))
+                .collect::<Vec<_>>(),
+            [(3, 1)]
+        );
+    }
+}
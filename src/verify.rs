@@ -0,0 +1,253 @@
+//! Re-download the recorded assets and check them against their recorded digest.
+
+use std::fmt::Display;
+
+use crate::{extract::ExtractError, Arch, Digest, PkgInfo, VersionedArchEntry};
+
+/// A recorded asset to verify.
+#[derive(Debug, PartialEq, Eq)]
+pub struct VerifyTarget<'a> {
+    pub version: &'a str,
+    pub arch: Arch,
+    pub entry: &'a VersionedArchEntry<'a>,
+}
+
+/// Select the recorded assets matching the provided `versions` & `arches`.
+///
+/// An empty filter select every version (or arch).
+pub fn select_targets<'a>(
+    pkg_info: &'a PkgInfo<'_>,
+    versions: &[String],
+    arches: &[Arch],
+) -> Result<Vec<VerifyTarget<'a>>, ExtractError> {
+    use itertools::Itertools;
+
+    let Some(recorded_versions) = pkg_info.base.versions.as_ref() else {
+        return match versions.first() {
+            Some(version) => Err(ExtractError::UnknownVersion(version.clone())),
+            None => Ok(Vec::new()),
+        };
+    };
+
+    if let Some(version) = versions
+        .iter()
+        .find(|version| !recorded_versions.contains_key(version.as_str()))
+    {
+        return Err(ExtractError::UnknownVersion(version.clone()));
+    }
+
+    Ok(recorded_versions
+        .iter()
+        .filter(|(version, _)| {
+            versions.is_empty() || versions.iter().any(|v| v.as_str() == version.as_ref())
+        })
+        .sorted_by_key(|(version, _)| *version)
+        .flat_map(|(version, content)| {
+            content
+                .iter()
+                .filter(|(arch, _)| arches.is_empty() || arches.contains(arch))
+                .sorted_by_key(|(arch, _)| **arch)
+                .map(|(arch, entry)| VerifyTarget {
+                    version,
+                    arch: *arch,
+                    entry,
+                })
+        })
+        .collect())
+}
+
+/// The outcome of verifying a recorded asset.
+#[derive(Debug, PartialEq, Eq)]
+pub enum VerifyStatus {
+    /// The downloaded asset match the recorded digest.
+    Ok { size: usize },
+    /// The server responded with a non-success status code (e.g. `404`).
+    Unreachable(reqwest::StatusCode),
+    /// The request could not be completed.
+    RequestFailed(String),
+    /// The downloaded size differ from the size announced by the server (`Content-Length`).
+    SizeMismatch { expected: u64, got: usize },
+    /// The digest of the downloaded asset differ from the recorded one.
+    DigestMismatch { expected: String, got: String },
+}
+
+impl VerifyStatus {
+    pub fn is_ok(&self) -> bool {
+        matches!(self, VerifyStatus::Ok { .. })
+    }
+}
+
+impl Display for VerifyStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VerifyStatus::Ok { size } => write!(f, "ok ({size} bytes)"),
+            VerifyStatus::Unreachable(status) => write!(f, "unreachable ({status})"),
+            VerifyStatus::RequestFailed(error) => write!(f, "request failed ({error})"),
+            VerifyStatus::SizeMismatch { expected, got } => {
+                write!(f, "size mismatch (expected {expected} bytes, got {got})")
+            }
+            VerifyStatus::DigestMismatch { expected, got } => {
+                write!(f, "digest mismatch (expected {expected}, got {got})")
+            }
+        }
+    }
+}
+
+/// Download the asset and compare it with its recorded digest.
+pub async fn verify_entry(
+    http_client: &reqwest::Client,
+    entry: &VersionedArchEntry<'_>,
+) -> VerifyStatus {
+    let response = match http_client.get(entry.download_url.clone()).send().await {
+        Ok(response) => response,
+        Err(e) => return VerifyStatus::RequestFailed(e.to_string()),
+    };
+    if !response.status().is_success() {
+        return VerifyStatus::Unreachable(response.status());
+    }
+
+    // The client does not decode the body, so the announced length is the one of the asset.
+    let content_length = response.content_length();
+    let (size, digest) =
+        match crate::reqwest_utils::digest_response(response, entry.digest.algorithm()).await {
+            Ok(res) => res,
+            Err(e) => return VerifyStatus::RequestFailed(e.to_string()),
+        };
+
+    check_download(entry, content_length, size, &digest)
+}
+
+/// Compare the downloaded `size` & `digest` with the announced `content_length` & the recorded digest.
+fn check_download(
+    entry: &VersionedArchEntry<'_>,
+    content_length: Option<u64>,
+    size: usize,
+    digest: &Digest<'_>,
+) -> VerifyStatus {
+    if let Some(expected) = content_length.filter(|expected| *expected != size as u64) {
+        VerifyStatus::SizeMismatch {
+            expected,
+            got: size,
+        }
+    } else if *digest != entry.digest {
+        VerifyStatus::DigestMismatch {
+            expected: entry.digest.to_string(),
+            got: digest.to_string(),
+        }
+    } else {
+        VerifyStatus::Ok { size }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rstest::rstest;
+
+    const PKG_INFO: &str = r#"{
+  "name": "Foobar",
+  "latest_version": "1.1.0",
+  "versions": {
+    "1.1.0": {
+      "arm64": {
+        "filename": "foobar-arm64",
+        "download_url": "https://example.com/1.1.0/foobar-arm64",
+        "digest": "sha256:25f5602ea53a18f4d64208c6d135690ace28cda7b89ef1eeccd2e60e6cce2e03"
+      },
+      "amd64": {
+        "filename": "foobar-amd64",
+        "download_url": "https://example.com/1.1.0/foobar-amd64",
+        "digest": "sha256:e8bf04349572f90e569c5bd46be3f7101e1e289125adb8b9eaba94badba1c43a"
+      }
+    },
+    "1.0.0": {
+      "amd64": {
+        "filename": "foobar-amd64",
+        "download_url": "https://example.com/1.0.0/foobar-amd64",
+        "digest": "sha256:e8bf04349572f90e569c5bd46be3f7101e1e289125adb8b9eaba94badba1c43a"
+      }
+    }
+  },
+  "mode": "bash-command",
+  "command": "true"
+}"#;
+
+    fn summarize<'a>(targets: Vec<VerifyTarget<'a>>) -> Vec<(&'a str, Arch)> {
+        targets
+            .into_iter()
+            .map(|target| (target.version, target.arch))
+            .collect()
+    }
+
+    #[test]
+    fn select_all_targets() {
+        let pkg_info = serde_json::from_str::<PkgInfo>(PKG_INFO).unwrap();
+
+        let targets = select_targets(&pkg_info, &[], &[]).unwrap();
+
+        assert_eq!(
+            summarize(targets),
+            [
+                ("1.0.0", Arch::Amd64),
+                ("1.1.0", Arch::Amd64),
+                ("1.1.0", Arch::Arm64)
+            ]
+        );
+    }
+
+    #[test]
+    fn select_filtered_targets() {
+        let pkg_info = serde_json::from_str::<PkgInfo>(PKG_INFO).unwrap();
+
+        let targets = select_targets(&pkg_info, &["1.1.0".into()], &[Arch::Arm64]).unwrap();
+
+        assert_eq!(summarize(targets), [("1.1.0", Arch::Arm64)]);
+    }
+
+    #[test]
+    fn select_unknown_version() {
+        let pkg_info = serde_json::from_str::<PkgInfo>(PKG_INFO).unwrap();
+
+        assert_eq!(
+            select_targets(&pkg_info, &["2.0.0".into()], &[]),
+            Err(ExtractError::UnknownVersion("2.0.0".into()))
+        );
+    }
+
+    const RECORDED_DIGEST: &str =
+        "sha256:e8bf04349572f90e569c5bd46be3f7101e1e289125adb8b9eaba94badba1c43a";
+    const OTHER_DIGEST: &str =
+        "sha256:25f5602ea53a18f4d64208c6d135690ace28cda7b89ef1eeccd2e60e6cce2e03";
+
+    #[rstest]
+    #[case::ok(Some(4), 4, RECORDED_DIGEST, VerifyStatus::Ok { size: 4 })]
+    #[case::no_content_length(None, 4, RECORDED_DIGEST, VerifyStatus::Ok { size: 4 })]
+    #[case::size_changed(
+        Some(5),
+        4,
+        RECORDED_DIGEST,
+        VerifyStatus::SizeMismatch { expected: 5, got: 4 }
+    )]
+    #[case::digest_changed(
+        Some(4),
+        4,
+        OTHER_DIGEST,
+        VerifyStatus::DigestMismatch { expected: RECORDED_DIGEST.into(), got: OTHER_DIGEST.into() }
+    )]
+    fn test_check_download(
+        #[case] content_length: Option<u64>,
+        #[case] size: usize,
+        #[case] digest: &str,
+        #[case] expected: VerifyStatus,
+    ) {
+        let pkg_info = serde_json::from_str::<PkgInfo>(PKG_INFO).unwrap();
+        let entry = &pkg_info.base.versions.as_ref().unwrap()["1.0.0"][&Arch::Amd64];
+        let digest = Digest::try_from(digest).unwrap();
+
+        assert_eq!(
+            check_download(entry, content_length, size, &digest),
+            expected
+        );
+    }
+}
@@ -1,4 +1,10 @@
-use std::borrow::Cow;
+use std::{borrow::Cow, cmp::Ordering, fmt::Display, str::FromStr};
+
+use crate::pkg_info::PkgOption;
+
+mod transform;
+
+pub use transform::{TransformError, VersionTransform};
 
 #[derive(Debug)]
 pub struct RawVersion<'a>(Cow<'a, str>);
@@ -13,19 +19,548 @@ impl<'a> From<Cow<'a, str>> for RawVersion<'a> {
 pub struct Version<'a>(Cow<'a, str>);
 
 impl<'a> Version<'a> {
-    pub fn from_raw_str(raw: Cow<'a, str>, strip_v_prefix: bool) -> Self {
-        if strip_v_prefix && raw.starts_with('v') {
-            Self(Cow::Owned(raw[1..].to_owned()))
-        } else {
-            Self(raw)
-        }
+    /// Apply `strip_v_prefix` then the `version_transform` steps of `option` to `raw`.
+    pub fn from_raw_str(raw: Cow<'a, str>, option: &PkgOption) -> Result<Self, TransformError> {
+        let raw = match raw {
+            Cow::Borrowed(raw) if option.strip_v_prefix => {
+                Cow::Borrowed(raw.strip_prefix('v').unwrap_or(raw))
+            }
+            Cow::Owned(raw) if option.strip_v_prefix && raw.starts_with('v') => {
+                Cow::Owned(raw[1..].to_owned())
+            }
+            raw => raw,
+        };
+        option
+            .version_transform
+            .iter()
+            .try_fold(raw, |version, transform| transform.apply(version))
+            .map(Self)
     }
 
-    pub fn from_raw(raw: RawVersion<'a>, strip_v_prefix: bool) -> Self {
-        Self::from_raw_str(raw.0, strip_v_prefix)
+    pub fn from_raw(raw: RawVersion<'a>, option: &PkgOption) -> Result<Self, TransformError> {
+        Self::from_raw_str(raw.0, option)
     }
 
     pub fn as_str(&self) -> &str {
         self.0.as_ref()
     }
 }
+
+/// A version parsed for comparison, either semver, loose dotted numeric (`1.2`) or calver (`2024.01.15`).
+///
+/// A `v` prefix and the build metadata (`+...`) are ignored.
+/// The prerelease part follows a `-` (`1.0.0-rc.1`) or is directly appended (`1.0.0rc1`),
+/// it lowers the precedence of the version.
+#[derive(Debug, Clone)]
+pub struct ParsedVersion {
+    release: Vec<u64>,
+    prerelease: Vec<Identifier>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Identifier {
+    // Numeric identifiers have a lower precedence than alphanumeric ones.
+    Numeric(u64),
+    Alphanumeric(String),
+}
+
+impl ParsedVersion {
+    pub fn is_prerelease(&self) -> bool {
+        !self.prerelease.is_empty()
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("Invalid version `{}`", .0)]
+pub struct InvalidVersion(String);
+
+impl FromStr for ParsedVersion {
+    type Err = InvalidVersion;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || InvalidVersion(s.to_owned());
+        let raw = s.strip_prefix(['v', 'V']).unwrap_or(s);
+        let raw = raw.split_once('+').map_or(raw, |(raw, _build)| raw);
+
+        let release_end = raw
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(raw.len());
+        let (release, prerelease) = raw.split_at(release_end);
+        let release = release.strip_suffix('.').unwrap_or(release);
+        let prerelease = prerelease.trim_start_matches(['-', '.', '_']);
+        if release.is_empty() || (release_end < raw.len() && prerelease.is_empty()) {
+            return Err(invalid());
+        }
+
+        let release = release
+            .split('.')
+            .map(|part| part.parse::<u64>().map_err(|_| invalid()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let prerelease = prerelease
+            .split(['.', '-'])
+            .flat_map(split_numeric_parts)
+            .map(|part| {
+                if !part.bytes().all(|c| c.is_ascii_alphanumeric()) {
+                    Err(invalid())
+                } else if let Ok(num) = part.parse() {
+                    Ok(Identifier::Numeric(num))
+                } else {
+                    Ok(Identifier::Alphanumeric(part.to_ascii_lowercase()))
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            release,
+            prerelease,
+        })
+    }
+}
+
+impl ParsedVersion {
+    /// Compare the release parts only, missing parts are considered as `0` (`1.2` == `1.2.0`).
+    fn release_cmp(&self, other: &Self) -> Ordering {
+        let len = self.release.len().max(other.release.len());
+        let release_part = |version: &Self, i| version.release.get(i).copied().unwrap_or(0);
+
+        (0..len)
+            .map(|i| release_part(self, i).cmp(&release_part(other, i)))
+            .find(|ord| ord.is_ne())
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+impl Ord for ParsedVersion {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.release_cmp(other)
+            .then_with(|| match (self.is_prerelease(), other.is_prerelease()) {
+                (false, false) => Ordering::Equal,
+                (false, true) => Ordering::Greater,
+                (true, false) => Ordering::Less,
+                (true, true) => self.prerelease.cmp(&other.prerelease),
+            })
+    }
+}
+
+impl PartialOrd for ParsedVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for ParsedVersion {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for ParsedVersion {}
+
+/// A range of versions, a list of comma separated comparators that must all be satisfied.
+///
+/// Supported comparators: `=`, `>`, `>=`, `<`, `<=`, `~1.2` (`>=1.2, <1.3`), `^1.2` (`>=1.2, <2`),
+/// `1.2.*` (`>=1.2, <1.3`) and `*` (any version). A bare version is a caret comparator.
+/// An upper bound excludes the prereleases of its version (`<2` does not match `2.0.0-rc.1`).
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct VersionConstraint {
+    raw: String,
+    comparators: Vec<Comparator>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Comparator {
+    op: Op,
+    version: ParsedVersion,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Exact,
+    Greater,
+    GreaterEq,
+    Less,
+    LessEq,
+}
+
+impl VersionConstraint {
+    /// Check if `version` is in the range, an unparseable version never is.
+    pub fn matches(&self, version: &str) -> bool {
+        version
+            .parse::<ParsedVersion>()
+            .is_ok_and(|version| self.matches_parsed(&version))
+    }
+
+    pub fn matches_parsed(&self, version: &ParsedVersion) -> bool {
+        self.comparators.iter().all(|comparator| {
+            let bound = &comparator.version;
+            match comparator.op {
+                Op::Exact => version == bound,
+                Op::Greater => version > bound,
+                Op::GreaterEq => version >= bound,
+                Op::Less if !bound.is_prerelease() => version.release_cmp(bound).is_lt(),
+                Op::Less => version < bound,
+                Op::LessEq => version <= bound,
+            }
+        })
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum InvalidConstraint {
+    #[error("Empty comparator in version constraint")]
+    EmptyComparator,
+    #[error("Invalid version constraint: {}", .0)]
+    InvalidVersion(#[from] InvalidVersion),
+    #[error("The upper bound of the version constraint `{}` overflows", .0)]
+    UpperBoundOverflow(String),
+}
+
+impl FromStr for VersionConstraint {
+    type Err = InvalidConstraint;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut comparators = Vec::new();
+
+        for raw_comparator in s.split(',').map(str::trim) {
+            if raw_comparator == "*" {
+                continue;
+            }
+            let (op, raw_version) = [">=", "<=", ">", "<", "=", "~", "^"]
+                .into_iter()
+                .find_map(|op| Some((op, raw_comparator.strip_prefix(op)?)))
+                .unwrap_or(("^", raw_comparator));
+            let raw_version = raw_version.trim();
+            if raw_version.is_empty() {
+                return Err(InvalidConstraint::EmptyComparator);
+            }
+            let (op, raw_version) = match raw_version
+                .strip_suffix(".*")
+                .or_else(|| raw_version.strip_suffix(".x"))
+            {
+                Some(prefix) if matches!(op, "=" | "^") => ("~", prefix),
+                _ => (op, raw_version),
+            };
+            let version = raw_version.parse::<ParsedVersion>()?;
+
+            let mut push = |op, version| comparators.push(Comparator { op, version });
+            match op {
+                ">=" => push(Op::GreaterEq, version),
+                "<=" => push(Op::LessEq, version),
+                ">" => push(Op::Greater, version),
+                "<" => push(Op::Less, version),
+                "=" => push(Op::Exact, version),
+                _ => {
+                    let upper_bound = if op == "~" {
+                        tilde_upper_bound(&version)
+                    } else {
+                        caret_upper_bound(&version)
+                    }
+                    .ok_or_else(|| InvalidConstraint::UpperBoundOverflow(raw_comparator.into()))?;
+                    push(Op::GreaterEq, version);
+                    push(Op::Less, upper_bound);
+                }
+            }
+        }
+
+        Ok(Self {
+            raw: s.to_owned(),
+            comparators,
+        })
+    }
+}
+
+/// `~1.2.3` & `~1.2` allow patch changes, `~1` allow minor changes.
+fn tilde_upper_bound(version: &ParsedVersion) -> Option<ParsedVersion> {
+    let kept = version.release.len().clamp(1, 2);
+    increment_release(version, kept - 1)
+}
+
+/// `^1.2.3` allow changes not modifying the first non-zero part (`^0.2.3` allow patch changes).
+fn caret_upper_bound(version: &ParsedVersion) -> Option<ParsedVersion> {
+    let index = version
+        .release
+        .iter()
+        .position(|part| *part != 0)
+        .unwrap_or(version.release.len() - 1);
+    increment_release(version, index)
+}
+
+/// The release truncated after `index` with that part incremented, `None` on overflow.
+fn increment_release(version: &ParsedVersion, index: usize) -> Option<ParsedVersion> {
+    let mut release = version.release[..=index].to_vec();
+    release[index] = release[index].checked_add(1)?;
+    Some(ParsedVersion {
+        release,
+        prerelease: Vec::new(),
+    })
+}
+
+impl TryFrom<String> for VersionConstraint {
+    type Error = InvalidConstraint;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<VersionConstraint> for String {
+    fn from(value: VersionConstraint) -> Self {
+        value.raw
+    }
+}
+
+impl Display for VersionConstraint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.raw)
+    }
+}
+
+impl schemars::JsonSchema for VersionConstraint {
+    fn schema_name() -> Cow<'static, str> {
+        Cow::Borrowed("VersionConstraint")
+    }
+
+    fn json_schema(_generator: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        schemars::json_schema!({
+            "description": "A comma separated list of comparators (`=`, `>`, `>=`, `<`, `<=`, `~`, `^`, `*`) the version must satisfy.",
+            "type": "string",
+            "examples": [">=1.4, <2", "~3.2"]
+        })
+    }
+}
+
+/// Compare two version strings by precedence, falling back to [`natural_cmp`] if one cannot be parsed.
+pub fn compare(a: &str, b: &str) -> Ordering {
+    match (a.parse::<ParsedVersion>(), b.parse::<ParsedVersion>()) {
+        (Ok(a_version), Ok(b_version)) => a_version.cmp(&b_version),
+        _ => natural_cmp(a, b),
+    }
+}
+
+/// A total order to sort version strings.
+///
+/// Versions are ordered by precedence (ties broken by their string),
+/// the unparseable ones are placed after them in string order.
+pub fn sort_cmp(a: &str, b: &str) -> Ordering {
+    match (a.parse::<ParsedVersion>(), b.parse::<ParsedVersion>()) {
+        (Ok(a_version), Ok(b_version)) => a_version.cmp(&b_version).then_with(|| a.cmp(b)),
+        (Ok(_), Err(_)) => Ordering::Less,
+        (Err(_), Ok(_)) => Ordering::Greater,
+        (Err(_), Err(_)) => a.cmp(b),
+    }
+}
+
+/// Compare two version strings, the numeric parts are compared by value.
+///
+/// e.g. `1.10.0` is greater than `1.9.2`.
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a_parts = split_numeric_parts(a);
+    let mut b_parts = split_numeric_parts(b);
+
+    loop {
+        match (a_parts.next(), b_parts.next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(a_part), Some(b_part)) => {
+                let ord = match (a_part.parse::<u64>(), b_part.parse::<u64>()) {
+                    (Ok(a_num), Ok(b_num)) => a_num.cmp(&b_num),
+                    _ => a_part.cmp(b_part),
+                };
+                if ord.is_ne() {
+                    return ord;
+                }
+            }
+        }
+    }
+}
+
+/// The first numeric part of a version string (e.g. `1` for `v1.2.3`).
+pub fn major(version: &str) -> Option<u64> {
+    split_numeric_parts(version).find_map(|part| part.parse().ok())
+}
+
+/// Split a string into alternating runs of digits & non-digits.
+fn split_numeric_parts(s: &str) -> impl Iterator<Item = &str> {
+    let mut rest = s;
+    std::iter::from_fn(move || {
+        let first = rest.chars().next()?;
+        let is_digit = first.is_ascii_digit();
+        let end = rest
+            .find(|c: char| c.is_ascii_digit() != is_digit)
+            .unwrap_or(rest.len());
+        let (part, tail) = rest.split_at(end);
+        rest = tail;
+        Some(part)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::equal("1.2.3", "1.2.3", Ordering::Equal)]
+    #[case::numeric("1.10.0", "1.9.2", Ordering::Greater)]
+    #[case::shorter("1.2", "1.2.1", Ordering::Less)]
+    #[case::v_prefix("v2.0.0", "v10.0.0", Ordering::Less)]
+    #[case::calver("2024.01.15", "2023.12.31", Ordering::Greater)]
+    fn test_natural_cmp(#[case] a: &str, #[case] b: &str, #[case] expected: Ordering) {
+        assert_eq!(natural_cmp(a, b), expected);
+        assert_eq!(natural_cmp(b, a), expected.reverse());
+    }
+
+    #[rstest]
+    #[case::semver("1.2.3", "1.2.3", &[1, 2, 3], false)]
+    #[case::v_prefix("v1.2", "1.2", &[1, 2], false)]
+    #[case::calver("2024.01.15", "2024.1.15", &[2024, 1, 15], false)]
+    #[case::build_metadata("1.0.0+build.5", "1.0.0", &[1, 0, 0], false)]
+    #[case::prerelease("1.0.0-rc.1", "1.0.0-rc.1", &[1, 0, 0], true)]
+    #[case::glued_prerelease("1.0.0rc1", "1.0.0-rc.1", &[1, 0, 0], true)]
+    fn test_parse_version(
+        #[case] raw: &str,
+        #[case] equivalent: &str,
+        #[case] release: &[u64],
+        #[case] is_prerelease: bool,
+    ) {
+        let version = raw.parse::<ParsedVersion>().unwrap();
+
+        assert_eq!(version.release, release);
+        assert_eq!(version.is_prerelease(), is_prerelease);
+        assert_eq!(version, equivalent.parse::<ParsedVersion>().unwrap());
+    }
+
+    #[rstest]
+    #[case::empty("")]
+    #[case::no_number("latest")]
+    #[case::empty_part("1..2")]
+    #[case::dangling_separator("1.0.0-")]
+    #[case::invalid_prerelease("1.0.0-rc/1")]
+    fn test_parse_invalid_version(#[case] raw: &str) {
+        assert!(raw.parse::<ParsedVersion>().is_err());
+    }
+
+    #[rstest]
+    #[case::patch("1.2.10", "1.2.9", Ordering::Greater)]
+    #[case::missing_part("1.2", "1.2.0", Ordering::Equal)]
+    #[case::prerelease_lower("1.0.0-rc.1", "1.0.0", Ordering::Less)]
+    #[case::prerelease_numeric("1.0.0-rc.10", "1.0.0-rc.2", Ordering::Greater)]
+    #[case::prerelease_glued_numeric("1.0.0rc10", "1.0.0rc2", Ordering::Greater)]
+    #[case::prerelease_alpha("1.0.0-alpha", "1.0.0-beta", Ordering::Less)]
+    #[case::prerelease_numeric_before_alpha("1.0.0-1", "1.0.0-alpha", Ordering::Less)]
+    #[case::prerelease_longer("1.0.0-alpha.1", "1.0.0-alpha", Ordering::Greater)]
+    #[case::prerelease_of_next("1.1.0-rc.1", "1.0.9", Ordering::Greater)]
+    #[case::backport("1.4.8", "2.0.1", Ordering::Less)]
+    #[case::fallback("release-10", "release-9", Ordering::Greater)]
+    fn test_compare(#[case] a: &str, #[case] b: &str, #[case] expected: Ordering) {
+        assert_eq!(compare(a, b), expected);
+        assert_eq!(compare(b, a), expected.reverse());
+    }
+
+    #[test]
+    fn test_sort_cmp() {
+        let mut versions = [
+            "nightly",
+            "10.0.0",
+            "9.0.0",
+            "v1.2",
+            "1.2.0",
+            "2.0.0-rc.10",
+            "2.0.0-rc.2",
+            "2.0.0",
+            "beta",
+        ];
+
+        versions.sort_by(|a, b| sort_cmp(a, b));
+
+        assert_eq!(
+            versions,
+            [
+                "1.2.0",
+                "v1.2",
+                "2.0.0-rc.2",
+                "2.0.0-rc.10",
+                "2.0.0",
+                "9.0.0",
+                "10.0.0",
+                "beta",
+                "nightly"
+            ]
+        );
+    }
+
+    #[rstest]
+    #[case::range(">=1.4, <2", &["1.4.0", "1.9.9", "v1.10"], &["1.3.9", "2.0.0", "2.0.0-rc.1"])]
+    #[case::tilde("~3.2", &["3.2.0", "3.2.15"], &["3.1.9", "3.3.0"])]
+    #[case::tilde_major("~3", &["3.0.0", "3.9.1"], &["2.9.0", "4.0.0"])]
+    #[case::caret("^1.4", &["1.4.0", "1.99.0"], &["1.3.0", "2.0.0"])]
+    #[case::caret_zero("^0.4.1", &["0.4.1", "0.4.9"], &["0.4.0", "0.5.0"])]
+    #[case::bare("16", &["16.0.0", "16.4.1"], &["15.9.0", "17.0.0"])]
+    #[case::wildcard("1.2.*", &["1.2.0", "1.2.9"], &["1.1.0", "1.3.0"])]
+    #[case::exact("=1.2", &["1.2.0"], &["1.2.1"])]
+    #[case::any("*", &["0.1.0", "99.0.0"], &["nightly"])]
+    #[case::prerelease_bound("<2.0.0-rc.2", &["2.0.0-rc.1"], &["2.0.0-rc.2"])]
+    fn test_version_constraint(
+        #[case] raw: &str,
+        #[case] matching: &[&str],
+        #[case] not_matching: &[&str],
+    ) {
+        let constraint = raw.parse::<VersionConstraint>().unwrap();
+
+        for version in matching {
+            assert!(constraint.matches(version), "{raw} should match {version}");
+        }
+        for version in not_matching {
+            assert!(
+                !constraint.matches(version),
+                "{raw} should not match {version}"
+            );
+        }
+    }
+
+    #[rstest]
+    #[case::empty_comparator(">=1, ")]
+    #[case::missing_version(">=")]
+    #[case::invalid_version("~latest")]
+    #[case::tilde_overflow("~18446744073709551615")]
+    #[case::caret_overflow("^0.18446744073709551615")]
+    fn test_invalid_version_constraint(#[case] raw: &str) {
+        assert!(raw.parse::<VersionConstraint>().is_err());
+    }
+
+    #[rstest]
+    #[case::semver("1.2.3", Some(1))]
+    #[case::v_prefix("v12.0.1", Some(12))]
+    #[case::no_number("latest", None)]
+    fn test_major(#[case] version: &str, #[case] expected: Option<u64>) {
+        assert_eq!(major(version), expected);
+    }
+
+    #[rstest]
+    #[case::none(r#"{}"#, "v1.2.3", "v1.2.3")]
+    #[case::strip_v_prefix(r#"{ "strip_v_prefix": true }"#, "v1.2.3", "1.2.3")]
+    #[case::strip_v_prefix_first(
+        r#"{ "strip_v_prefix": true, "version_transform": [{ "strip_prefix": "V" }] }"#,
+        "vV1.2.3",
+        "1.2.3"
+    )]
+    #[case::in_order(
+        r#"{ "version_transform": ["lowercase", { "strip_suffix": "-final" }] }"#,
+        "1.2.3-FINAL",
+        "1.2.3"
+    )]
+    fn test_version_from_raw_str(
+        #[case] raw_option: &str,
+        #[case] raw: &str,
+        #[case] expected: &str,
+    ) {
+        let option = serde_json::from_str::<PkgOption>(raw_option).unwrap();
+
+        let version = Version::from_raw_str(Cow::Borrowed(raw), &option).unwrap();
+
+        assert_eq!(version.as_str(), expected);
+    }
+}
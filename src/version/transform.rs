@@ -0,0 +1,140 @@
+//! Declarative steps turning the raw version provided by a mode into the recorded version.
+
+use std::borrow::Cow;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// A step of the version transform pipeline.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum VersionTransform {
+    /// Remove a prefix from the version if present.
+    StripPrefix(String),
+    /// Remove a suffix from the version if present.
+    StripSuffix(String),
+    /// Replace every match of a regex, `$1` or `${name}` refer to a capture group.
+    Replace {
+        #[serde(with = "crate::serde_utils::regex_str")]
+        #[schemars(with = "String", extend("format" = "regex"))]
+        pattern: regex::Regex,
+        replacement: String,
+    },
+    /// Convert the version to lowercase.
+    Lowercase,
+    /// Fail if the version does not match the regex.
+    Validate(
+        #[serde(with = "crate::serde_utils::regex_str")]
+        #[schemars(with = "String", extend("format" = "regex"))]
+        regex::Regex,
+    ),
+}
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+#[error("Version `{}` does not match the pattern `{}`", .version, .pattern)]
+pub struct TransformError {
+    pub version: String,
+    pub pattern: String,
+}
+
+impl VersionTransform {
+    pub fn apply<'a>(&self, version: Cow<'a, str>) -> Result<Cow<'a, str>, TransformError> {
+        let transformed = match self {
+            VersionTransform::StripPrefix(prefix) => {
+                version.strip_prefix(prefix.as_str()).map(ToOwned::to_owned)
+            }
+            VersionTransform::StripSuffix(suffix) => {
+                version.strip_suffix(suffix.as_str()).map(ToOwned::to_owned)
+            }
+            VersionTransform::Replace {
+                pattern,
+                replacement,
+            } => match pattern.replace_all(&version, replacement.as_str()) {
+                Cow::Borrowed(_) => None,
+                Cow::Owned(replaced) => Some(replaced),
+            },
+            VersionTransform::Lowercase => version
+                .chars()
+                .any(char::is_uppercase)
+                .then(|| version.to_lowercase()),
+            VersionTransform::Validate(pattern) => {
+                if !pattern.is_match(&version) {
+                    return Err(TransformError {
+                        version: version.into_owned(),
+                        pattern: pattern.to_string(),
+                    });
+                }
+                None
+            }
+        };
+
+        Ok(transformed.map_or(version, Cow::Owned))
+    }
+}
+
+impl PartialEq for VersionTransform {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::StripPrefix(a), Self::StripPrefix(b))
+            | (Self::StripSuffix(a), Self::StripSuffix(b)) => a == b,
+            (
+                Self::Replace {
+                    pattern: a_pattern,
+                    replacement: a_replacement,
+                },
+                Self::Replace {
+                    pattern: b_pattern,
+                    replacement: b_replacement,
+                },
+            ) => a_pattern.as_str() == b_pattern.as_str() && a_replacement == b_replacement,
+            (Self::Lowercase, Self::Lowercase) => true,
+            (Self::Validate(a), Self::Validate(b)) => a.as_str() == b.as_str(),
+            _ => false,
+        }
+    }
+}
+
+impl Eq for VersionTransform {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::strip_prefix(r#"{ "strip_prefix": "release-" }"#, "release-1.2", "1.2")]
+    #[case::strip_prefix_absent(r#"{ "strip_prefix": "release-" }"#, "1.2", "1.2")]
+    #[case::strip_suffix(r#"{ "strip_suffix": "-stable" }"#, "1.2-stable", "1.2")]
+    #[case::replace(
+        r#"{ "replace": { "pattern": "_", "replacement": "." } }"#,
+        "1_2_3",
+        "1.2.3"
+    )]
+    #[case::replace_capture(
+        r#"{ "replace": { "pattern": "^cli/v(.+)$", "replacement": "$1" } }"#,
+        "cli/v1.2.3",
+        "1.2.3"
+    )]
+    #[case::lowercase(r#""lowercase""#, "V1.2-RC1", "v1.2-rc1")]
+    #[case::validate(r#"{ "validate": "^[0-9.]+$" }"#, "1.2.3", "1.2.3")]
+    fn test_apply(#[case] raw_transform: &str, #[case] version: &str, #[case] expected: &str) {
+        let transform = serde_json::from_str::<VersionTransform>(raw_transform).unwrap();
+
+        assert_eq!(transform.apply(Cow::Borrowed(version)).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_validate_reject() {
+        let transform =
+            serde_json::from_str::<VersionTransform>(r#"{ "validate": "^[0-9.]+$" }"#).unwrap();
+
+        assert_eq!(
+            transform.apply(Cow::Borrowed("nightly")),
+            Err(TransformError {
+                version: "nightly".into(),
+                pattern: "^[0-9.]+$".into()
+            })
+        );
+    }
+}
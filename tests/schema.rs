@@ -1,11 +1,13 @@
-use std::borrow::Cow;
+use std::{borrow::Cow, num::NonZeroUsize};
 
 use regex::Regex;
 use rstest::rstest;
 
 use pkg_info_updater::{
-    Arch, BashCmdReleaseHandler, Digest, GithubReleaseHandler, JqScriptReleaseHandler, PkgInfo,
-    PkgInfoBase, PkgInfoMode, PkgOption, VersionedArchEntry,
+    pkg_info::{MissingArchPolicy, ReleaseField, VersionRegex},
+    Arch, BashCmdReleaseHandler, Digest, GithubReleaseHandler, GithubVersionSource,
+    JqScriptReleaseHandler, PkgInfo, PkgInfoBase, PkgInfoMode, PkgOption, PrereleasePolicy,
+    Retention, VersionTransform, VersionedArchEntry,
 };
 
 #[rstest]
@@ -16,7 +18,8 @@ use pkg_info_updater::{
             schema: Some("../../pkg-info.schema.json"),
             name: "Gohugo",
             latest_version: None,
-            versions: None
+            versions: None,
+            retention: None
         },
         option: PkgOption::default(),
         mode: PkgInfoMode::GithubRelease(GithubReleaseHandler {
@@ -24,7 +27,13 @@ use pkg_info_updater::{
             arch_asset_patterns: [
                 (Arch::Amd64, Regex::new("^hugo_([0-9]+(\\.[0-9]+)+)_linux-amd64.tar.gz$").unwrap()),
                 (Arch::Arm64, Regex::new("^hugo_([0-9]+(\\.[0-9]+)+)_linux-arm64.tar.gz$").unwrap())
-            ].into_iter().collect()
+            ].into_iter().collect(),
+            version_source: GithubVersionSource::default(),
+            required_arches: None,
+            missing_arch_policy: MissingArchPolicy::default(),
+            api_base_url: None,
+            download_through_api: false,
+            download_url_template: None
         })
     }
 )]
@@ -35,7 +44,8 @@ use pkg_info_updater::{
             schema: None,
             name: "Foobar",
             latest_version: None,
-            versions: None
+            versions: None,
+            retention: None
         },
         option: PkgOption::default(),
         mode: PkgInfoMode::BashCommand(BashCmdReleaseHandler {
@@ -50,7 +60,8 @@ use pkg_info_updater::{
             schema: Some("../../pkg-info.schema.json"),
             name: "Sonarr",
             latest_version: None,
-            versions: None
+            versions: None,
+            retention: None
         },
         option: PkgOption::default(),
         mode: PkgInfoMode::JqScript(JqScriptReleaseHandler {
@@ -85,7 +96,8 @@ use pkg_info_updater::{
                         }
                     )
                 ].into_iter().collect())
-            ].into_iter().collect())
+            ].into_iter().collect()),
+            retention: None
         },
         option: PkgOption::default(),
         mode: PkgInfoMode::GithubRelease(GithubReleaseHandler {
@@ -93,7 +105,13 @@ use pkg_info_updater::{
             arch_asset_patterns: [
                 (Arch::Amd64, Regex::new("^hugo_([0-9]+(\\.[0-9]+)+)_linux-amd64.tar.gz$").unwrap()),
                 (Arch::Arm64, Regex::new("^hugo_([0-9]+(\\.[0-9]+)+)_linux-arm64.tar.gz$").unwrap())
-            ].into_iter().collect()
+            ].into_iter().collect(),
+            version_source: GithubVersionSource::default(),
+            required_arches: None,
+            missing_arch_policy: MissingArchPolicy::default(),
+            api_base_url: None,
+            download_through_api: false,
+            download_url_template: None
         })
     }
 )]
@@ -115,7 +133,8 @@ use pkg_info_updater::{
                         }
                     ),
                 ].into_iter().collect())
-            ].into_iter().collect())
+            ].into_iter().collect()),
+            retention: None
         },
         option: PkgOption {
             strip_v_prefix: true,
@@ -125,7 +144,13 @@ use pkg_info_updater::{
             repository_path: "gohugoio/hugo",
             arch_asset_patterns: [
                 (Arch::Amd64, Regex::new("^hugo_([0-9]+(\\.[0-9]+)+)_linux-amd64.tar.gz$").unwrap()),
-            ].into_iter().collect()
+            ].into_iter().collect(),
+            version_source: GithubVersionSource::default(),
+            required_arches: None,
+            missing_arch_policy: MissingArchPolicy::default(),
+            api_base_url: None,
+            download_through_api: false,
+            download_url_template: None
         })
     }
 )]
@@ -136,7 +161,8 @@ use pkg_info_updater::{
             schema: Some("../../pkg-info.schema.json"),
             name: "ArgoCD",
             latest_version: None,
-            versions: Some([].into_iter().collect())
+            versions: Some([].into_iter().collect()),
+            retention: None
         },
         option: PkgOption {
             allow_prerelease: true,
@@ -146,7 +172,255 @@ use pkg_info_updater::{
             repository_path: "argoproj/argo-cd",
             arch_asset_patterns: [
                 (Arch::Amd64, Regex::new("^argocd-linux-amd64$").unwrap()),
-            ].into_iter().collect()
+            ].into_iter().collect(),
+            version_source: GithubVersionSource::default(),
+            required_arches: None,
+            missing_arch_policy: MissingArchPolicy::default(),
+            api_base_url: None,
+            download_through_api: false,
+            download_url_template: None
+        })
+    }
+)]
+#[case::prerelease_policy(
+    std::include_str!("samples/prerelease-policy.json"),
+    PkgInfo {
+        base: PkgInfoBase {
+            schema: Some("../../pkg-info.schema.json"),
+            name: "ArgoCD",
+            latest_version: None,
+            versions: Some([].into_iter().collect()),
+            retention: None
+        },
+        option: PkgOption {
+            prerelease_policy: Some(PrereleasePolicy::PreferStable),
+            ..Default::default()
+        },
+        mode: PkgInfoMode::GithubRelease(GithubReleaseHandler {
+            repository_path: "argoproj/argo-cd",
+            arch_asset_patterns: [
+                (Arch::Amd64, Regex::new("^argocd-linux-amd64$").unwrap()),
+            ].into_iter().collect(),
+            version_source: GithubVersionSource::default(),
+            required_arches: None,
+            missing_arch_policy: MissingArchPolicy::default(),
+            api_base_url: None,
+            download_through_api: false,
+            download_url_template: None
+        })
+    }
+)]
+#[case::retention(
+    std::include_str!("samples/retention.json"),
+    PkgInfo {
+        base: PkgInfoBase {
+            schema: Some("../../pkg-info.schema.json"),
+            name: "Gitea",
+            latest_version: None,
+            versions: Some([].into_iter().collect()),
+            retention: Some(Retention {
+                keep_last: NonZeroUsize::new(3),
+                keep_latest_per_major: true,
+            })
+        },
+        option: PkgOption::default(),
+        mode: PkgInfoMode::GithubRelease(GithubReleaseHandler {
+            repository_path: "go-gitea/gitea",
+            arch_asset_patterns: [
+                (Arch::Amd64, Regex::new("^gitea-[0-9.]+-linux-amd64$").unwrap()),
+            ].into_iter().collect(),
+            version_source: GithubVersionSource::default(),
+            required_arches: None,
+            missing_arch_policy: MissingArchPolicy::default(),
+            api_base_url: None,
+            download_through_api: false,
+            download_url_template: None
+        })
+    }
+)]
+#[case::extension_fields(
+    std::include_str!("samples/extension-fields.json"),
+    PkgInfo {
+        base: PkgInfoBase {
+            schema: Some("../../pkg-info.schema.json"),
+            name: "Foobar",
+            latest_version: None,
+            versions: None,
+            retention: None
+        },
+        option: PkgOption::default(),
+        mode: PkgInfoMode::BashCommand(BashCmdReleaseHandler {
+            command: Cow::Borrowed(r#"echo '{ "version": "0.1.0", "assets": {} }'"#)
+        })
+    }
+)]
+#[case::version_constraint(
+    std::include_str!("samples/version-constraint.json"),
+    PkgInfo {
+        base: PkgInfoBase {
+            schema: Some("../../pkg-info.schema.json"),
+            name: "PostgreSQL client",
+            latest_version: None,
+            versions: Some([].into_iter().collect()),
+            retention: None
+        },
+        option: PkgOption {
+            version_constraint: Some(">=16.0, <17".parse().unwrap()),
+            ..Default::default()
+        },
+        mode: PkgInfoMode::GithubRelease(GithubReleaseHandler {
+            repository_path: "postgres/postgres",
+            arch_asset_patterns: [
+                (Arch::Amd64, Regex::new("^postgresql-[0-9.]+-linux-amd64.tar.gz$").unwrap()),
+            ].into_iter().collect(),
+            version_source: GithubVersionSource::default(),
+            required_arches: None,
+            missing_arch_policy: MissingArchPolicy::default(),
+            api_base_url: None,
+            download_through_api: false,
+            download_url_template: None
+        })
+    }
+)]
+#[case::version_source(
+    std::include_str!("samples/version-source.json"),
+    PkgInfo {
+        base: PkgInfoBase {
+            schema: Some("../../pkg-info.schema.json"),
+            name: "Gohugo",
+            latest_version: None,
+            versions: None,
+            retention: None
+        },
+        option: PkgOption::default(),
+        mode: PkgInfoMode::GithubRelease(GithubReleaseHandler {
+            repository_path: "gohugoio/hugo",
+            arch_asset_patterns: [
+                (Arch::Amd64, Regex::new("^hugo_([0-9]+(\\.[0-9]+)+)_linux-amd64.tar.gz$").unwrap()),
+            ].into_iter().collect(),
+            version_source: GithubVersionSource::Regex(VersionRegex {
+                pattern: Regex::new("^Hugo v(?<version>\\S+)").unwrap(),
+                field: ReleaseField::Name,
+            }),
+            required_arches: None,
+            missing_arch_policy: MissingArchPolicy::default(),
+            api_base_url: None,
+            download_through_api: false,
+            download_url_template: None
+        })
+    }
+)]
+#[case::version_transform(
+    std::include_str!("samples/version-transform.json"),
+    PkgInfo {
+        base: PkgInfoBase {
+            schema: Some("../../pkg-info.schema.json"),
+            name: "Kustomize",
+            latest_version: None,
+            versions: None,
+            retention: None
+        },
+        option: PkgOption {
+            version_transform: vec![
+                VersionTransform::StripPrefix("kustomize/".into()),
+                VersionTransform::Replace {
+                    pattern: Regex::new("^v").unwrap(),
+                    replacement: "".into(),
+                },
+                VersionTransform::Lowercase,
+                VersionTransform::Validate(Regex::new("^[0-9]+\\.[0-9]+\\.[0-9]+$").unwrap()),
+            ],
+            ..Default::default()
+        },
+        mode: PkgInfoMode::GithubRelease(GithubReleaseHandler {
+            repository_path: "kubernetes-sigs/kustomize",
+            arch_asset_patterns: [
+                (Arch::Amd64, Regex::new("^kustomize_v[0-9.]+_linux_amd64.tar.gz$").unwrap()),
+            ].into_iter().collect(),
+            version_source: GithubVersionSource::TagName,
+            required_arches: None,
+            missing_arch_policy: MissingArchPolicy::default(),
+            api_base_url: None,
+            download_through_api: false,
+            download_url_template: None
+        })
+    }
+)]
+#[case::required_arches(
+    std::include_str!("samples/required-arches.json"),
+    PkgInfo {
+        base: PkgInfoBase {
+            schema: Some("../../pkg-info.schema.json"),
+            name: "Gohugo",
+            latest_version: None,
+            versions: None,
+            retention: None
+        },
+        option: PkgOption::default(),
+        mode: PkgInfoMode::GithubRelease(GithubReleaseHandler {
+            repository_path: "gohugoio/hugo",
+            arch_asset_patterns: [
+                (Arch::Amd64, Regex::new("^hugo_([0-9]+(\\.[0-9]+)+)_linux-amd64.tar.gz$").unwrap()),
+                (Arch::Arm64, Regex::new("^hugo_([0-9]+(\\.[0-9]+)+)_linux-arm64.tar.gz$").unwrap())
+            ].into_iter().collect(),
+            version_source: GithubVersionSource::default(),
+            required_arches: Some(vec![Arch::Amd64]),
+            missing_arch_policy: MissingArchPolicy::Fallback,
+            api_base_url: None,
+            download_through_api: false,
+            download_url_template: None
+        })
+    }
+)]
+#[case::github_enterprise(
+    std::include_str!("samples/github-enterprise.json"),
+    PkgInfo {
+        base: PkgInfoBase {
+            schema: Some("../../pkg-info.schema.json"),
+            name: "Internal CLI",
+            latest_version: None,
+            versions: None,
+            retention: None
+        },
+        option: PkgOption::default(),
+        mode: PkgInfoMode::GithubRelease(GithubReleaseHandler {
+            repository_path: "platform/internal-cli",
+            arch_asset_patterns: [
+                (Arch::Amd64, Regex::new("^internal-cli-linux-amd64$").unwrap()),
+            ].into_iter().collect(),
+            version_source: GithubVersionSource::default(),
+            required_arches: None,
+            missing_arch_policy: MissingArchPolicy::default(),
+            api_base_url: Some("https://github.example.com/api/v3".parse().unwrap()),
+            download_through_api: false,
+            download_url_template: None
+        })
+    }
+)]
+#[case::private_repository(
+    std::include_str!("samples/private-repository.json"),
+    PkgInfo {
+        base: PkgInfoBase {
+            schema: Some("../../pkg-info.schema.json"),
+            name: "Internal CLI",
+            latest_version: None,
+            versions: None,
+            retention: None
+        },
+        option: PkgOption::default(),
+        mode: PkgInfoMode::GithubRelease(GithubReleaseHandler {
+            repository_path: "platform/internal-cli",
+            arch_asset_patterns: [
+                (Arch::Amd64, Regex::new("^internal-cli-linux-amd64$").unwrap()),
+            ].into_iter().collect(),
+            version_source: GithubVersionSource::default(),
+            required_arches: None,
+            missing_arch_policy: MissingArchPolicy::default(),
+            api_base_url: None,
+            download_through_api: true,
+            download_url_template: Some(
+                "https://artifacts.example.com/internal-cli/{tag_name}/{filename}".into()
+            )
         })
     }
 )]
@@ -154,3 +428,41 @@ fn schema(#[case] input: &str, #[case] expected: PkgInfo) {
     let got = serde_json::from_str::<PkgInfo>(input).unwrap();
     assert_eq!(got, expected);
 }
+
+#[rstest]
+fn validate_samples(
+    #[values(
+        std::include_str!("samples/minimal-github-release.json"),
+        std::include_str!("samples/minimal-bash-command.json"),
+        std::include_str!("samples/minimal-jq-script.json"),
+        std::include_str!("samples/single-version-github-release.json"),
+        std::include_str!("samples/v-prefixed-version.json"),
+        std::include_str!("samples/allow-prerelease.json"),
+        std::include_str!("samples/prerelease-policy.json"),
+        std::include_str!("samples/retention.json"),
+        std::include_str!("samples/extension-fields.json"),
+        std::include_str!("samples/version-constraint.json"),
+        std::include_str!("samples/version-source.json"),
+        std::include_str!("samples/version-transform.json"),
+        std::include_str!("samples/required-arches.json"),
+        std::include_str!("samples/github-enterprise.json"),
+        std::include_str!("samples/private-repository.json"),
+        std::include_str!("../pkg-info.json")
+    )]
+    input: &str,
+) {
+    let validator = pkg_info_updater::validate::Validator::new().unwrap();
+
+    assert_eq!(validator.validate(input), []);
+}
+
+#[test]
+fn committed_schema_is_up_to_date() {
+    let committed = std::include_str!("../pkg-info.schema.json");
+    let generated = pkg_info_updater::schema::generate_pretty();
+
+    assert!(
+        committed == generated,
+        "`pkg-info.schema.json` is outdated, regenerate it with `cargo run -- schema > pkg-info.schema.json`"
+    );
+}